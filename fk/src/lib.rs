@@ -1,27 +1,69 @@
 use std::{
-  cell::RefCell,
+  cell::{Cell, RefCell},
   collections::{HashMap, HashSet},
+  hash::Hash,
   mem,
   sync::Arc,
+  time::Duration,
 };
 
-use fk_core::{KeyCode, *};
+use fk_core::{abi_stable_types::RawSlice, KeyCode, ButtonState, *};
 
 use bevy::{
-  asset::{AssetId, AssetPath, DirectAssetAccessExt, Handle, StrongHandle},
+  app::{App, Last, Plugin, PreUpdate},
+  asset::{
+    Asset, AssetEvent, AssetEvents, AssetId, AssetPath, Assets, DirectAssetAccessExt, Handle,
+    StrongHandle, UntypedHandle,
+  },
   audio::{AudioPlayer, AudioSource, PlaybackSettings, Volume},
-  color::Srgba,
+  color::{Color, LinearRgba, Srgba},
+  core::FrameCount,
   core_pipeline::core_3d::Camera3d,
-  ecs::{entity::Entity as BevyEntity, world::World},
+  ecs::{
+    bundle::Bundle,
+    entity::Entity as BevyEntity,
+    event::EventReader,
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{ResMut, Resource},
+    world::World,
+  },
+  gltf::GltfAssetLabel,
+  hierarchy::{BuildChildren, Children, DespawnRecursiveExt, Parent},
   image::Image,
   input::{
-    keyboard::{KeyCode as BevyKeyCode, NativeKeyCode},
-    ButtonInput,
+    gamepad::{Gamepad, GamepadAxis as BevyGamepadAxis, GamepadButton as BevyGamepadButton},
+    keyboard::{Key, KeyCode as BevyKeyCode, KeyboardInput, NativeKeyCode},
+    mouse::{
+      AccumulatedMouseMotion, AccumulatedMouseScroll, MouseButton as BevyMouseButton,
+      MouseScrollUnit as BevyMouseScrollUnit,
+    },
+    ButtonInput, ButtonState as BevyButtonState, InputSystem,
+  },
+  math::{
+    primitives::{Capsule3d, Circle, Cone, Cuboid, Cylinder, Plane3d, Rectangle, Sphere, Torus},
+    UVec2,
+  },
+  pbr::{
+    AmbientLight as BevyAmbientLight, CascadeShadowConfig, CascadeShadowConfigBuilder,
+    DirectionalLight as BevyDirectionalLight, MeshMaterial3d, PointLight as BevyPointLight,
+    SpotLight as BevySpotLight, StandardMaterial, MAX_CASCADES_PER_LIGHT,
   },
-  math::primitives::{Cuboid, Plane3d, Sphere},
-  pbr::{MeshMaterial3d, StandardMaterial},
-  render::mesh::{Mesh, Mesh3d, Meshable},
+  render::{
+    mesh::{Indices, Mesh, Mesh3d, Meshable, PrimitiveTopology},
+    alpha::AlphaMode as BevyAlphaMode,
+    camera::{
+      Camera as BevyCamera, ClearColor, ClearColorConfig, OrthographicProjection,
+      PerspectiveProjection, Projection as BevyProjection, ScalingMode, Viewport as BevyViewport,
+    },
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat},
+    view::Visibility,
+  },
+  scene::{Scene, SceneRoot},
+  time::{Fixed, Time},
   transform::components::Transform,
+  window::{PrimaryWindow, Window},
 };
 
 pub fn entity_to_bevy(entity: Entity) -> BevyEntity {
@@ -36,7 +78,24 @@ thread_local! {
   static CURRENT_WORLD: RefCell<World> = def();
   static EMPTY_WORLD: RefCell<Option<World>> = RefCell::new(Some(World::new()));
   static ASSET_HANDLES: RefCell<HashMap<BevyRawAssetIndex, Arc<StrongHandle>>> = def();
+  static NEXT_ASSET_INDEX: Cell<BevyRawAssetIndex> = def();
   static ENTITIES: RefCell<HashSet<BevyEntity>> = def();
+  static RAW_KEY_EVENTS: RefCell<Vec<RawKeyEvent>> = def();
+  static GAMEPADS: RefCell<Vec<RawEntity>> = def();
+}
+
+/// Systems and resources needed for the functions of this crate
+pub struct FkPlugin;
+
+impl Plugin for FkPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<FrameKeyEvents>()
+      .init_resource::<Actions>()
+      .init_resource::<AssetCache>()
+      .add_systems(PreUpdate, collect_key_events.after(InputSystem))
+      .add_systems(Last, prune_asset_cache.after(AssetEvents));
+  }
 }
 
 pub fn take_world(world: &mut World) -> impl FnOnce(&mut World) + use<> {
@@ -60,21 +119,146 @@ fn use_world<R>(use_: impl FnOnce(&mut World) -> R) -> R {
   })
 }
 
-// TODO: cache assets
+/// [`Shape`] compared by bits of its fields, so it can be used as a key of [`AssetCache`]
+#[derive(PartialEq, Eq, Hash)]
+struct ShapeKey(mem::Discriminant<Shape>, [u32; 3]);
+
+impl From<&Shape> for ShapeKey {
+  fn from(shape: &Shape) -> Self {
+    let bits = |fields: [f32; 3]| fields.map(f32::to_bits);
+    let fields = match *shape {
+      Shape::Cuboid(size) => bits(size.to_array()),
+      Shape::Sphere(a) | Shape::Circle(a) => bits([a, 0.0, 0.0]),
+      Shape::Plane(a, b)
+      | Shape::Cylinder(a, b)
+      | Shape::Capsule(a, b)
+      | Shape::Cone(a, b)
+      | Shape::Torus(a, b)
+      | Shape::Rectangle(a, b) => bits([a, b, 0.0]),
+      Shape::UvSphere(radius, sectors, stacks) => [radius.to_bits(), sectors, stacks],
+    };
+    Self(mem::discriminant(shape), fields)
+  }
+}
+
+/// Meshes and materials shared between spawned entities.
+/// The cache doesn't keep them alive, they are dropped with the last entity which uses them.
+#[derive(Resource, Default)]
+pub struct AssetCache {
+  meshes: HashMap<ShapeKey, AssetId<Mesh>>,
+  color_materials: HashMap<Rgba, AssetId<StandardMaterial>>,
+  image_materials: HashMap<AssetId<Image>, AssetId<StandardMaterial>>,
+  hits: u64,
+  misses: u64,
+}
+
+impl AssetCache {
+  /// Number of cached meshes which are currently alive
+  pub fn meshes(&self) -> usize {
+    self.meshes.len()
+  }
+
+  /// Number of cached materials of [`spawn_color_mesh`] which are currently alive
+  pub fn color_materials(&self) -> usize {
+    self.color_materials.len()
+  }
+
+  /// Number of cached materials of [`spawn_image_mesh`] which are currently alive
+  pub fn image_materials(&self) -> usize {
+    self.image_materials.len()
+  }
+
+  /// How many times an existing asset was reused
+  pub fn hits(&self) -> u64 {
+    self.hits
+  }
+
+  /// How many times a new asset was created
+  pub fn misses(&self) -> u64 {
+    self.misses
+  }
+}
+
+/// Returns a new strong handle of the cached asset or adds a new one
+fn cached_asset<K: Eq + Hash, A: Asset>(
+  world: &mut World,
+  cache: impl Fn(&mut AssetCache) -> &mut HashMap<K, AssetId<A>>,
+  key: K,
+  create: impl FnOnce() -> A,
+) -> Handle<A> {
+  let id = cache(&mut world.resource_mut::<AssetCache>())
+    .get(&key)
+    .copied();
+  // asset may already be dropped but not yet removed from the cache
+  let handle = id.and_then(|id| world.resource_mut::<Assets<A>>().get_strong_handle(id));
+
+  let mut asset_cache = world.resource_mut::<AssetCache>();
+  if let Some(handle) = handle {
+    asset_cache.hits += 1;
+    return handle;
+  }
+  asset_cache.misses += 1;
+
+  let handle = world.add_asset(create());
+  cache(&mut world.resource_mut::<AssetCache>()).insert(key, handle.id());
+  handle
+}
+
+fn prune_asset_cache(
+  mut cache: ResMut<AssetCache>,
+  mut mesh_events: EventReader<AssetEvent<Mesh>>,
+  mut material_events: EventReader<AssetEvent<StandardMaterial>>,
+) {
+  for event in mesh_events.read() {
+    if let AssetEvent::Removed { id } = event {
+      cache.meshes.retain(|_, cached| cached != id);
+    }
+  }
+  for event in material_events.read() {
+    if let AssetEvent::Removed { id } = event {
+      cache.color_materials.retain(|_, cached| cached != id);
+      cache.image_materials.retain(|_, cached| cached != id);
+    }
+  }
+}
+
+fn cached_color_material(world: &mut World, color: Rgba) -> Handle<StandardMaterial> {
+  cached_asset(
+    world,
+    |cache| &mut cache.color_materials,
+    color,
+    || StandardMaterial {
+      base_color: color_to_bevy(color),
+      ..def()
+    },
+  )
+}
+
+fn shape_mesh(shape: &Shape) -> Mesh {
+  match *shape {
+    Shape::Cuboid(size) => Cuboid::from_size(size).into(),
+    Shape::Plane(width, height) => Plane3d::default().mesh().size(width, height).into(),
+    Shape::Sphere(radius) => Sphere::new(radius).into(),
+    Shape::UvSphere(radius, sectors, stacks) => Sphere::new(radius).mesh().uv(sectors, stacks),
+    Shape::Cylinder(radius, height) => Cylinder::new(radius, height).into(),
+    Shape::Capsule(radius, length) => Capsule3d::new(radius, length).into(),
+    Shape::Cone(radius, height) => Cone { radius, height }.into(),
+    Shape::Torus(inner_radius, outer_radius) => Torus::new(inner_radius, outer_radius).into(),
+    Shape::Circle(radius) => Circle::new(radius).into(),
+    Shape::Rectangle(width, height) => Rectangle::new(width, height).into(),
+  }
+}
+
 pub fn spawn_color_mesh(transform: Transform, shape: &Shape, color: Rgba) -> Entity {
   use_world(|world| {
-    let mesh: Mesh = match shape {
-      Shape::Cuboid(size) => Cuboid::from_size(*size).into(),
-      Shape::Plane(width, height) => Plane3d::default().mesh().size(*width, *height).into(),
-      Shape::Sphere(radius) => Sphere::new(*radius).into(),
-    };
-    let mesh: Handle<Mesh> = world.add_asset(mesh);
+    let mesh = cached_asset(
+      world,
+      |cache| &mut cache.meshes,
+      shape.into(),
+      || shape_mesh(shape),
+    );
     let mesh = Mesh3d(mesh);
-    let material: Handle<StandardMaterial> = world.add_asset(StandardMaterial {
-      base_color: Srgba::rgba_u8(color.0, color.1, color.2, color.3).into(),
-      ..def()
-    });
-    let material = MeshMaterial3d(material);
+    let material = MeshMaterial3d(cached_color_material(world, color));
 
     let entity = world.spawn((transform, mesh, material)).id();
     ENTITIES.with_borrow_mut(|entities| entities.insert(entity));
@@ -84,26 +268,85 @@ pub fn spawn_color_mesh(transform: Transform, shape: &Shape, color: Rgba) -> Ent
   })
 }
 
-// TODO: abstract away from bevy Image
-// TODO: cache assets
-pub fn spawn_image_mesh(transform: Transform, shape: Shape, image: Image) -> Entity {
+pub fn create_image(
+  width: u32,
+  height: u32,
+  format: ImageFormat,
+  pixels: &[u8],
+) -> BevyRawAssetIndex {
+  let (format, pixel_size) = match format {
+    ImageFormat::Rgba8Srgb => (TextureFormat::Rgba8UnormSrgb, 4),
+    ImageFormat::Rgba8 => (TextureFormat::Rgba8Unorm, 4),
+    ImageFormat::R8 => (TextureFormat::R8Unorm, 1),
+  };
+  let size = (width as usize)
+    .checked_mul(height as usize)
+    .and_then(|size| size.checked_mul(pixel_size));
+
+  let image = if size == Some(pixels.len()) {
+    Image::new(
+      Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+      },
+      TextureDimension::D2,
+      pixels.to_vec(),
+      format,
+      RenderAssetUsages::RENDER_WORLD,
+    )
+  } else {
+    eprintln!(
+      "failed to create image: size {width}x{height} doesn't match {} bytes of its pixels",
+      pixels.len()
+    );
+    Image::default()
+  };
   use_world(|world| {
-    let mesh: Mesh = match shape {
-      Shape::Cuboid(size) => Cuboid::from_size(size).into(),
-      Shape::Plane(width, height) => Plane3d::default().mesh().size(width, height).into(),
-      Shape::Sphere(radius) => Sphere::new(radius).into(),
-    };
-    let mesh: Handle<Mesh> = world.add_asset(mesh);
+    let handle: Handle<Image> = world.add_asset(image);
+    insert_asset_handle(handle)
+  })
+}
+
+pub fn load_image_asset(path: &str) -> BevyRawAssetIndex {
+  use_world(|world| {
+    let handle: Handle<Image> = world.load_asset(path);
+    insert_asset_handle(handle)
+  })
+}
+
+/// Spawns a mesh textured with the image created by [`create_image`] or [`load_image_asset`]
+pub fn spawn_image_mesh(transform: Transform, shape: &Shape, image: BevyRawAssetIndex) -> Entity {
+  use_world(|world| {
+    let mesh = cached_asset(
+      world,
+      |cache| &mut cache.meshes,
+      shape.into(),
+      || shape_mesh(shape),
+    );
     let mesh = Mesh3d(mesh);
 
-    let texture: Handle<Image> = world.add_asset(image);
-    let material: Handle<StandardMaterial> = world.add_asset(StandardMaterial {
-      base_color_texture: Some(texture),
-      ..def()
-    });
-    let material = MeshMaterial3d(material);
+    let material = match asset_handle::<Image>(image) {
+      Ok(image) => Some(cached_asset(
+        world,
+        |cache| &mut cache.image_materials,
+        image.id(),
+        || StandardMaterial {
+          base_color_texture: Some(image),
+          ..def()
+        },
+      )),
+      Err(e) => {
+        eprintln!("spawning image mesh without material: {e}");
+        None
+      }
+    };
 
-    let entity = world.spawn((transform, mesh, material)).id();
+    let mut entity = world.spawn((transform, mesh));
+    if let Some(material) = material {
+      entity.insert(MeshMaterial3d(material));
+    }
+    let entity = entity.id();
 
     ENTITIES.with_borrow_mut(|entities| entities.insert(entity));
 
@@ -112,59 +355,991 @@ pub fn spawn_image_mesh(transform: Transform, shape: Shape, image: Image) -> Ent
   })
 }
 
-pub fn spawn_point_light(transform: Transform, light: &PointLight) -> Entity {
+fn standard_material(material: &Material) -> StandardMaterial {
+  let Rgba(r, g, b, a) = material.base_color;
+  let base_color = Srgba::rgba_u8(r, g, b, a);
+
+  let Rgba(r, g, b, _) = material.emissive;
+  let emissive = LinearRgba::from(Srgba::rgb_u8(r, g, b));
+  let intensity = material.emissive_intensity;
+  let emissive = LinearRgba::rgb(
+    emissive.red * intensity,
+    emissive.green * intensity,
+    emissive.blue * intensity,
+  );
+
+  let base_color_texture =
+    Option::<BevyRawAssetIndex>::from(material.base_color_texture).and_then(|image| {
+      asset_handle::<Image>(image)
+        .inspect_err(|e| eprintln!("material has no texture: {e}"))
+        .ok()
+    });
+
+  let alpha_mode = match material.alpha_mode {
+    AlphaMode::Opaque => BevyAlphaMode::Opaque,
+    AlphaMode::Mask(threshold) => BevyAlphaMode::Mask(threshold),
+    AlphaMode::Blend => BevyAlphaMode::Blend,
+  };
+
+  StandardMaterial {
+    base_color: base_color.into(),
+    base_color_texture,
+    emissive,
+    metallic: material.metallic,
+    perceptual_roughness: material.roughness,
+    alpha_mode,
+    unlit: material.unlit,
+    ..def()
+  }
+}
+
+pub fn create_material(material: &Material) -> BevyRawAssetIndex {
+  let material = standard_material(material);
   use_world(|world| {
-    let entity = world
-      .spawn((
-        transform,
-        bevy::pbr::PointLight {
-          intensity: light.intensity,
-          range: light.range,
-          shadows_enabled: light.shadows_enabled,
-          shadow_depth_bias: light.shadow_depth_bias,
-          color: Srgba::rgba_u8(light.color.0, light.color.1, light.color.2, light.color.3).into(),
-          ..def()
-        },
-      ))
-      .id();
+    let handle: Handle<StandardMaterial> = world.add_asset(material);
+    insert_asset_handle(handle)
+  })
+}
 
+pub fn update_material(material: BevyRawAssetIndex, data: &Material) {
+  let data = standard_material(data);
+  use_world(|world| {
+    let handle = match asset_handle::<StandardMaterial>(material) {
+      Ok(handle) => handle,
+      Err(e) => {
+        eprintln!("failed to update material: {e}");
+        return;
+      }
+    };
+    let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+    match materials.get_mut(&handle) {
+      Some(material) => *material = data,
+      None => eprintln!("failed to update material: it doesn't exist"),
+    }
+  })
+}
+
+pub fn spawn_material_mesh(
+  transform: Transform,
+  shape: &Shape,
+  material: BevyRawAssetIndex,
+) -> Entity {
+  use_world(|world| {
+    let mesh = cached_asset(
+      world,
+      |cache| &mut cache.meshes,
+      shape.into(),
+      || shape_mesh(shape),
+    );
+    let mesh = Mesh3d(mesh);
+
+    let mut entity = world.spawn((transform, mesh));
+    match asset_handle::<StandardMaterial>(material) {
+      Ok(material) => {
+        entity.insert(MeshMaterial3d(material));
+      }
+      Err(e) => eprintln!("spawning material mesh without material: {e}"),
+    }
+    let entity = entity.id();
     ENTITIES.with_borrow_mut(|entities| entities.insert(entity));
 
-    let entity = bevy_to_entity(entity);
-    entity
+    bevy_to_entity(entity)
+  })
+}
+
+pub fn set_entity_material(entity: Entity, material: BevyRawAssetIndex) {
+  use_world(|world| {
+    let material = match asset_handle::<StandardMaterial>(material) {
+      Ok(material) => MeshMaterial3d(material),
+      Err(e) => {
+        eprintln!("failed to set material of entity: {e}");
+        return;
+      }
+    };
+    match world.get_entity_mut(entity_to_bevy(entity)) {
+      Ok(mut entity) => {
+        entity.insert(material);
+      }
+      Err(e) => eprintln!("failed to set material of entity: {e}"),
+    }
+  })
+}
+
+/// Normals and uvs can be empty (see [`RawMesh`])
+fn custom_mesh(
+  positions: &[Vec3],
+  normals: &[Vec3],
+  uvs: &[Vec2],
+  indices: &[u32],
+) -> Result<Mesh, String> {
+  if !normals.is_empty() && normals.len() != positions.len() {
+    return Err("mesh must have a normal for every position or no normals at all".to_owned());
+  }
+  if !uvs.is_empty() && uvs.len() != positions.len() {
+    return Err("mesh must have a uv for every position or no uvs at all".to_owned());
+  }
+  let vertices = if indices.is_empty() {
+    positions.len()
+  } else {
+    indices.len()
+  };
+  if vertices % 3 != 0 {
+    return Err("mesh must be a triangle list".to_owned());
+  }
+  if let Some(index) = indices
+    .iter()
+    .find(|&&index| index as usize >= positions.len())
+  {
+    return Err(format!(
+      "index {index} is out of range of {} positions",
+      positions.len()
+    ));
+  }
+
+  let mut mesh = Mesh::new(
+    PrimitiveTopology::TriangleList,
+    RenderAssetUsages::default(),
+  );
+  mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions.to_vec());
+  if !uvs.is_empty() {
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs.to_vec());
+  }
+  if !indices.is_empty() {
+    mesh.insert_indices(Indices::U32(indices.to_vec()));
+  }
+  if normals.is_empty() {
+    mesh.compute_normals();
+  } else {
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals.to_vec());
+  }
+  Ok(mesh)
+}
+
+pub fn create_mesh(
+  positions: &[Vec3],
+  normals: &[Vec3],
+  uvs: &[Vec2],
+  indices: &[u32],
+) -> BevyRawAssetIndex {
+  let mesh = custom_mesh(positions, normals, uvs, indices).unwrap_or_else(|e| {
+    eprintln!("failed to create mesh: {e}");
+    let mut mesh = Mesh::new(
+      PrimitiveTopology::TriangleList,
+      RenderAssetUsages::default(),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<Vec3>::new());
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, Vec::<Vec3>::new());
+    mesh
+  });
+  use_world(|world| {
+    let handle: Handle<Mesh> = world.add_asset(mesh);
+    insert_asset_handle(handle)
+  })
+}
+
+pub fn update_mesh(
+  mesh: BevyRawAssetIndex,
+  positions: &[Vec3],
+  normals: &[Vec3],
+  uvs: &[Vec2],
+  indices: &[u32],
+) {
+  let data = match custom_mesh(positions, normals, uvs, indices) {
+    Ok(data) => data,
+    Err(e) => {
+      eprintln!("failed to update mesh: {e}");
+      return;
+    }
+  };
+  use_world(|world| {
+    let handle = match asset_handle::<Mesh>(mesh) {
+      Ok(handle) => handle,
+      Err(e) => {
+        eprintln!("failed to update mesh: {e}");
+        return;
+      }
+    };
+    let mut meshes = world.resource_mut::<Assets<Mesh>>();
+    match meshes.get_mut(&handle) {
+      Some(mesh) => *mesh = data,
+      None => eprintln!("failed to update mesh: it doesn't exist"),
+    }
+  })
+}
+
+/// Spawns an entity with the mesh created by [`create_mesh`]
+pub fn spawn_mesh(transform: Transform, mesh: BevyRawAssetIndex, color: Rgba) -> Entity {
+  use_world(|world| {
+    let material = MeshMaterial3d(cached_color_material(world, color));
+
+    let mut entity = world.spawn((transform, material));
+    match asset_handle::<Mesh>(mesh) {
+      Ok(mesh) => {
+        entity.insert(Mesh3d(mesh));
+      }
+      Err(e) => eprintln!("spawning entity without mesh: {e}"),
+    }
+    let entity = entity.id();
+    ENTITIES.with_borrow_mut(|entities| entities.insert(entity));
+
+    bevy_to_entity(entity)
+  })
+}
+
+fn color_to_bevy(color: Rgba) -> Color {
+  Srgba::rgba_u8(color.0, color.1, color.2, color.3).into()
+}
+
+fn point_light_to_bevy(light: &PointLight) -> BevyPointLight {
+  BevyPointLight {
+    intensity: light.intensity,
+    range: light.range,
+    shadows_enabled: light.shadows_enabled,
+    shadow_depth_bias: light.shadow_depth_bias,
+    color: color_to_bevy(light.color),
+    ..def()
+  }
+}
+
+fn directional_light_to_bevy(
+  light: &DirectionalLight,
+) -> (BevyDirectionalLight, CascadeShadowConfig) {
+  let cascades = CascadeShadowConfigBuilder {
+    num_cascades: (light.shadow_cascades as usize).min(MAX_CASCADES_PER_LIGHT),
+    first_cascade_far_bound: light.first_cascade_far_bound,
+    maximum_distance: light.max_shadow_distance,
+    ..def()
+  };
+  // build() panics otherwise
+  let valid = cascades.num_cascades > 0
+    && (cascades.num_cascades == 1 || cascades.minimum_distance < cascades.first_cascade_far_bound)
+    && cascades.maximum_distance > cascades.minimum_distance;
+  let cascades = if valid {
+    cascades
+  } else {
+    eprintln!("invalid shadow cascades of directional light, default ones are used");
+    def()
+  };
+  let cascades = cascades.build();
+
+  let light = BevyDirectionalLight {
+    illuminance: light.illuminance,
+    color: color_to_bevy(light.color),
+    shadows_enabled: light.shadows_enabled,
+    shadow_depth_bias: light.shadow_depth_bias,
+    ..def()
+  };
+  (light, cascades)
+}
+
+fn spot_light_to_bevy(light: &SpotLight) -> BevySpotLight {
+  BevySpotLight {
+    intensity: light.intensity,
+    range: light.range,
+    color: color_to_bevy(light.color),
+    shadows_enabled: light.shadows_enabled,
+    shadow_depth_bias: light.shadow_depth_bias,
+    inner_angle: light.inner_angle,
+    outer_angle: light.outer_angle,
+    ..def()
+  }
+}
+
+fn spawn_light(transform: Transform, light: impl Bundle) -> Entity {
+  use_world(|world| {
+    let entity = world.spawn((transform, light)).id();
+    ENTITIES.with_borrow_mut(|entities| entities.insert(entity));
+
+    bevy_to_entity(entity)
+  })
+}
+
+/// Replaces components of the light (or adds them if the entity is not a light)
+fn set_light(entity: Entity, light: impl Bundle) {
+  use_world(|world| match world.get_entity_mut(entity_to_bevy(entity)) {
+    Ok(mut entity) => {
+      entity.insert(light);
+    }
+    Err(e) => eprintln!("failed to set light: {e}"),
+  })
+}
+
+pub fn spawn_point_light(transform: Transform, light: &PointLight) -> Entity {
+  spawn_light(transform, point_light_to_bevy(light))
+}
+
+pub fn set_point_light(entity: Entity, light: &PointLight) {
+  set_light(entity, point_light_to_bevy(light));
+}
+
+pub fn spawn_directional_light(transform: Transform, light: &DirectionalLight) -> Entity {
+  spawn_light(transform, directional_light_to_bevy(light))
+}
+
+pub fn set_directional_light(entity: Entity, light: &DirectionalLight) {
+  set_light(entity, directional_light_to_bevy(light));
+}
+
+pub fn spawn_spot_light(transform: Transform, light: &SpotLight) -> Entity {
+  spawn_light(transform, spot_light_to_bevy(light))
+}
+
+pub fn set_spot_light(entity: Entity, light: &SpotLight) {
+  set_light(entity, spot_light_to_bevy(light));
+}
+
+pub fn set_ambient_light(light: &AmbientLight) {
+  use_world(|world| {
+    world.insert_resource(BevyAmbientLight {
+      color: color_to_bevy(light.color),
+      brightness: light.brightness,
+    });
+  })
+}
+
+pub fn set_environment(environment: &Environment) {
+  use_world(|world| {
+    world.insert_resource(ClearColor(color_to_bevy(environment.clear_color)));
+  })
+}
+
+fn projection_to_bevy(camera: &Camera) -> BevyProjection {
+  let Camera { near, far, .. } = *camera;
+  // bevy or wgpu panic otherwise
+  let valid = near >= 0.0
+    && far > near
+    && far.is_finite()
+    && match camera.projection {
+      Projection::Perspective(fov) => near > 0.0 && fov > 0.0 && fov < std::f32::consts::PI,
+      Projection::Orthographic(height) => height > 0.0 && height.is_finite(),
+    };
+  if !valid {
+    eprintln!("invalid projection of camera, default one is used");
+    return projection_to_bevy(&Camera::default());
+  }
+
+  match camera.projection {
+    Projection::Perspective(fov) => BevyProjection::Perspective(PerspectiveProjection {
+      fov,
+      near,
+      far,
+      ..def()
+    }),
+    Projection::Orthographic(height) => BevyProjection::Orthographic(OrthographicProjection {
+      scaling_mode: ScalingMode::FixedVertical {
+        viewport_height: height,
+      },
+      near,
+      far,
+      ..OrthographicProjection::default_3d()
+    }),
+  }
+}
+
+/// Viewport is clamped to the window since wgpu panics if it's outside of the render target
+fn viewport_to_bevy(world: &mut World, camera: &Camera) -> Option<BevyViewport> {
+  let viewport: Viewport = Option::from(camera.viewport)?;
+  let window_size = world
+    .query_filtered::<&Window, With<PrimaryWindow>>()
+    .get_single(world)
+    .map(|window| window.physical_size())
+    // there is no window in headless apps
+    .unwrap_or(UVec2::MAX);
+
+  let position = UVec2::new(viewport.x, viewport.y).min(window_size);
+  let size = UVec2::new(viewport.width, viewport.height).min(window_size - position);
+  if size.x == 0 || size.y == 0 {
+    eprintln!("viewport of camera is empty or outside of the window, whole window is used");
+    return None;
+  }
+
+  Some(BevyViewport {
+    physical_position: position,
+    physical_size: size,
+    ..def()
+  })
+}
+
+/// Other fields of the bevy camera are kept
+fn update_bevy_camera(bevy: &mut BevyCamera, camera: &Camera, viewport: Option<BevyViewport>) {
+  bevy.order = camera.order as isize;
+  bevy.viewport = viewport;
+  bevy.clear_color = match Option::from(camera.clear_color) {
+    Some(color) => ClearColorConfig::Custom(color_to_bevy(color)),
+    None => ClearColorConfig::Default,
+  };
+}
+
+pub fn set_camera(entity: Entity, camera: &Camera) {
+  use_world(|world| {
+    let viewport = viewport_to_bevy(world, camera);
+    let entity = entity_to_bevy(entity);
+    let mut entity_mut = match world.get_entity_mut(entity) {
+      Ok(entity) => entity,
+      Err(e) => {
+        eprintln!("failed to set camera: {e}");
+        return;
+      }
+    };
+    let Some(mut bevy) = entity_mut.get_mut::<BevyCamera>() else {
+      eprintln!("failed to set camera: entity {entity} is not a camera");
+      return;
+    };
+    update_bevy_camera(&mut bevy, camera, viewport);
+    entity_mut.insert(projection_to_bevy(camera));
   })
 }
 
-pub fn spawn_camera(transform: Transform) -> Entity {
+pub fn spawn_camera(transform: Transform, camera: &Camera) -> Entity {
   use_world(|world| {
-    let entity = world.spawn((Camera3d::default(), transform)).id();
+    let mut bevy = BevyCamera::default();
+    update_bevy_camera(&mut bevy, camera, viewport_to_bevy(world, camera));
+    let entity = world
+      .spawn((
+        Camera3d::default(),
+        bevy,
+        projection_to_bevy(camera),
+        transform,
+      ))
+      .id();
     ENTITIES.with_borrow_mut(|entities| entities.insert(entity));
     let entity = bevy_to_entity(entity);
     entity
   })
 }
 
-pub fn key_pressed(key_code: KeyCode) -> bool {
-  let key_code: BevyKeyCode = {
-    let key_code = key_code as u32;
-    assert_ne!(
-      key_code as u32,
-      bevy_key_code_enum_discriminant(&BevyKeyCode::Unidentified(NativeKeyCode::Unidentified))
-    );
-    // max key code (variant_count is unstable)
-    assert!(key_code as u32 <= bevy_key_code_enum_discriminant(&BevyKeyCode::F35));
+// every variant is listed once so both matches are exhaustive, which means that
+// a variant added, removed or renamed in bevy (or in fk_core) is a compile error
+macro_rules! key_code_conversion {
+  ($($variant:ident),* $(,)?) => {
+    fn key_code_to_bevy(key_code: KeyCode) -> BevyKeyCode {
+      match key_code {
+        KeyCode::Unidentified => BevyKeyCode::Unidentified(NativeKeyCode::Unidentified),
+        $(KeyCode::$variant => BevyKeyCode::$variant,)*
+      }
+    }
 
-    // SAFETY: KeyCode enum is marked as repr(u32) so first u32 is always discriminant
-    // (checked in miri and it doesnt complain)
-    unsafe { std::mem::transmute::<[u32; 3], BevyKeyCode>([key_code, 0, 0]) }
+    /// Payload of bevy `Unidentified` is dropped
+    fn key_code_from_bevy(key_code: &BevyKeyCode) -> KeyCode {
+      match key_code {
+        BevyKeyCode::Unidentified(_) => KeyCode::Unidentified,
+        $(BevyKeyCode::$variant => KeyCode::$variant,)*
+      }
+    }
+
+    #[cfg(test)]
+    const KEY_CODES: &[KeyCode] = &[KeyCode::Unidentified, $(KeyCode::$variant,)*];
   };
+}
+
+key_code_conversion!(
+  Backquote,
+  Backslash,
+  BracketLeft,
+  BracketRight,
+  Comma,
+  Digit0,
+  Digit1,
+  Digit2,
+  Digit3,
+  Digit4,
+  Digit5,
+  Digit6,
+  Digit7,
+  Digit8,
+  Digit9,
+  Equal,
+  IntlBackslash,
+  IntlRo,
+  IntlYen,
+  KeyA,
+  KeyB,
+  KeyC,
+  KeyD,
+  KeyE,
+  KeyF,
+  KeyG,
+  KeyH,
+  KeyI,
+  KeyJ,
+  KeyK,
+  KeyL,
+  KeyM,
+  KeyN,
+  KeyO,
+  KeyP,
+  KeyQ,
+  KeyR,
+  KeyS,
+  KeyT,
+  KeyU,
+  KeyV,
+  KeyW,
+  KeyX,
+  KeyY,
+  KeyZ,
+  Minus,
+  Period,
+  Quote,
+  Semicolon,
+  Slash,
+  AltLeft,
+  AltRight,
+  Backspace,
+  CapsLock,
+  ContextMenu,
+  ControlLeft,
+  ControlRight,
+  Enter,
+  SuperLeft,
+  SuperRight,
+  ShiftLeft,
+  ShiftRight,
+  Space,
+  Tab,
+  Convert,
+  KanaMode,
+  Lang1,
+  Lang2,
+  Lang3,
+  Lang4,
+  Lang5,
+  NonConvert,
+  Delete,
+  End,
+  Help,
+  Home,
+  Insert,
+  PageDown,
+  PageUp,
+  ArrowDown,
+  ArrowLeft,
+  ArrowRight,
+  ArrowUp,
+  NumLock,
+  Numpad0,
+  Numpad1,
+  Numpad2,
+  Numpad3,
+  Numpad4,
+  Numpad5,
+  Numpad6,
+  Numpad7,
+  Numpad8,
+  Numpad9,
+  NumpadAdd,
+  NumpadBackspace,
+  NumpadClear,
+  NumpadClearEntry,
+  NumpadComma,
+  NumpadDecimal,
+  NumpadDivide,
+  NumpadEnter,
+  NumpadEqual,
+  NumpadHash,
+  NumpadMemoryAdd,
+  NumpadMemoryClear,
+  NumpadMemoryRecall,
+  NumpadMemoryStore,
+  NumpadMemorySubtract,
+  NumpadMultiply,
+  NumpadParenLeft,
+  NumpadParenRight,
+  NumpadStar,
+  NumpadSubtract,
+  Escape,
+  Fn,
+  FnLock,
+  PrintScreen,
+  ScrollLock,
+  Pause,
+  BrowserBack,
+  BrowserFavorites,
+  BrowserForward,
+  BrowserHome,
+  BrowserRefresh,
+  BrowserSearch,
+  BrowserStop,
+  Eject,
+  LaunchApp1,
+  LaunchApp2,
+  LaunchMail,
+  MediaPlayPause,
+  MediaSelect,
+  MediaStop,
+  MediaTrackNext,
+  MediaTrackPrevious,
+  Power,
+  Sleep,
+  AudioVolumeDown,
+  AudioVolumeMute,
+  AudioVolumeUp,
+  WakeUp,
+  Meta,
+  Hyper,
+  Turbo,
+  Abort,
+  Resume,
+  Suspend,
+  Again,
+  Copy,
+  Cut,
+  Find,
+  Open,
+  Paste,
+  Props,
+  Select,
+  Undo,
+  Hiragana,
+  Katakana,
+  F1,
+  F2,
+  F3,
+  F4,
+  F5,
+  F6,
+  F7,
+  F8,
+  F9,
+  F10,
+  F11,
+  F12,
+  F13,
+  F14,
+  F15,
+  F16,
+  F17,
+  F18,
+  F19,
+  F20,
+  F21,
+  F22,
+  F23,
+  F24,
+  F25,
+  F26,
+  F27,
+  F28,
+  F29,
+  F30,
+  F31,
+  F32,
+  F33,
+  F34,
+  F35,
+);
 
+pub fn key_pressed(key_code: KeyCode) -> bool {
+  let key_code = key_code_to_bevy(key_code);
   use_world(|world| {
     let input = world.resource::<ButtonInput<BevyKeyCode>>();
     input.pressed(key_code)
   })
 }
 
+pub fn key_just_pressed(key_code: KeyCode) -> bool {
+  let key_code = key_code_to_bevy(key_code);
+  use_world(|world| {
+    let input = world.resource::<ButtonInput<BevyKeyCode>>();
+    input.just_pressed(key_code)
+  })
+}
+
+pub fn key_just_released(key_code: KeyCode) -> bool {
+  let key_code = key_code_to_bevy(key_code);
+  use_world(|world| {
+    let input = world.resource::<ButtonInput<BevyKeyCode>>();
+    input.just_released(key_code)
+  })
+}
+
+fn mouse_button_to_bevy(button: MouseButton) -> BevyMouseButton {
+  match button {
+    MouseButton::Left => BevyMouseButton::Left,
+    MouseButton::Right => BevyMouseButton::Right,
+    MouseButton::Middle => BevyMouseButton::Middle,
+    MouseButton::Back => BevyMouseButton::Back,
+    MouseButton::Forward => BevyMouseButton::Forward,
+    MouseButton::Other(button) => BevyMouseButton::Other(button),
+  }
+}
+
+pub fn mouse_pressed(button: MouseButton) -> bool {
+  use_world(|world| {
+    let input = world.resource::<ButtonInput<BevyMouseButton>>();
+    input.pressed(mouse_button_to_bevy(button))
+  })
+}
+
+pub fn mouse_just_pressed(button: MouseButton) -> bool {
+  use_world(|world| {
+    let input = world.resource::<ButtonInput<BevyMouseButton>>();
+    input.just_pressed(mouse_button_to_bevy(button))
+  })
+}
+
+pub fn mouse_just_released(button: MouseButton) -> bool {
+  use_world(|world| {
+    let input = world.resource::<ButtonInput<BevyMouseButton>>();
+    input.just_released(mouse_button_to_bevy(button))
+  })
+}
+
+/// Position of the cursor in the primary window, see [`Window::cursor_position`]
+pub fn cursor_position() -> Option<Vec2> {
+  use_world(|world| {
+    let window = world
+      .query_filtered::<&Window, With<PrimaryWindow>>()
+      .get_single(world)
+      .ok()?;
+    window.cursor_position()
+  })
+}
+
+pub fn mouse_motion() -> Vec2 {
+  use_world(|world| world.resource::<AccumulatedMouseMotion>().delta)
+}
+
+pub fn mouse_scroll() -> MouseScroll {
+  use_world(|world| {
+    let scroll = world.resource::<AccumulatedMouseScroll>();
+    MouseScroll {
+      unit: match scroll.unit {
+        BevyMouseScrollUnit::Line => MouseScrollUnit::Line,
+        BevyMouseScrollUnit::Pixel => MouseScrollUnit::Pixel,
+      },
+      delta: scroll.delta,
+    }
+  })
+}
+
+fn gamepad_button_to_bevy(button: GamepadButton) -> BevyGamepadButton {
+  match button {
+    GamepadButton::South => BevyGamepadButton::South,
+    GamepadButton::East => BevyGamepadButton::East,
+    GamepadButton::North => BevyGamepadButton::North,
+    GamepadButton::West => BevyGamepadButton::West,
+    GamepadButton::C => BevyGamepadButton::C,
+    GamepadButton::Z => BevyGamepadButton::Z,
+    GamepadButton::LeftTrigger => BevyGamepadButton::LeftTrigger,
+    GamepadButton::LeftTrigger2 => BevyGamepadButton::LeftTrigger2,
+    GamepadButton::RightTrigger => BevyGamepadButton::RightTrigger,
+    GamepadButton::RightTrigger2 => BevyGamepadButton::RightTrigger2,
+    GamepadButton::Select => BevyGamepadButton::Select,
+    GamepadButton::Start => BevyGamepadButton::Start,
+    GamepadButton::Mode => BevyGamepadButton::Mode,
+    GamepadButton::LeftThumb => BevyGamepadButton::LeftThumb,
+    GamepadButton::RightThumb => BevyGamepadButton::RightThumb,
+    GamepadButton::DPadUp => BevyGamepadButton::DPadUp,
+    GamepadButton::DPadDown => BevyGamepadButton::DPadDown,
+    GamepadButton::DPadLeft => BevyGamepadButton::DPadLeft,
+    GamepadButton::DPadRight => BevyGamepadButton::DPadRight,
+    GamepadButton::Other(button) => BevyGamepadButton::Other(button),
+  }
+}
+
+fn gamepad_axis_to_bevy(axis: GamepadAxis) -> BevyGamepadAxis {
+  match axis {
+    GamepadAxis::LeftStickX => BevyGamepadAxis::LeftStickX,
+    GamepadAxis::LeftStickY => BevyGamepadAxis::LeftStickY,
+    GamepadAxis::LeftZ => BevyGamepadAxis::LeftZ,
+    GamepadAxis::RightStickX => BevyGamepadAxis::RightStickX,
+    GamepadAxis::RightStickY => BevyGamepadAxis::RightStickY,
+    GamepadAxis::RightZ => BevyGamepadAxis::RightZ,
+    GamepadAxis::Other(axis) => BevyGamepadAxis::Other(axis),
+  }
+}
+
+/// Returned memory is valid until the next call
+pub fn gamepads() -> RawSlice<RawEntity> {
+  use_world(|world| {
+    let mut query = world.query_filtered::<BevyEntity, With<Gamepad>>();
+    GAMEPADS.with_borrow_mut(|gamepads| {
+      gamepads.clear();
+      gamepads.extend(query.iter(world).map(|entity| bevy_to_entity(entity).0));
+      gamepads.as_slice().into()
+    })
+  })
+}
+
+/// Returns default value if the gamepad is disconnected
+fn use_gamepad<R: Default>(gamepad: Entity, use_: impl FnOnce(&Gamepad) -> R) -> R {
+  use_world(|world| {
+    world
+      .get::<Gamepad>(entity_to_bevy(gamepad))
+      .map(use_)
+      .unwrap_or_default()
+  })
+}
+
+pub fn gamepad_pressed(gamepad: Entity, button: GamepadButton) -> bool {
+  use_gamepad(gamepad, |gamepad| {
+    gamepad.pressed(gamepad_button_to_bevy(button))
+  })
+}
+
+pub fn gamepad_just_pressed(gamepad: Entity, button: GamepadButton) -> bool {
+  use_gamepad(gamepad, |gamepad| {
+    gamepad.just_pressed(gamepad_button_to_bevy(button))
+  })
+}
+
+pub fn gamepad_just_released(gamepad: Entity, button: GamepadButton) -> bool {
+  use_gamepad(gamepad, |gamepad| {
+    gamepad.just_released(gamepad_button_to_bevy(button))
+  })
+}
+
+pub fn gamepad_axis(gamepad: Entity, axis: GamepadAxis) -> f32 {
+  use_gamepad(gamepad, |gamepad| {
+    gamepad.get(gamepad_axis_to_bevy(axis)).unwrap_or_default()
+  })
+}
+
+/// Named actions and the buttons which trigger them, see [`action_pressed`]
+#[derive(Resource, Default)]
+pub struct Actions(pub HashMap<String, Vec<ActionBinding>>);
+
+#[derive(Debug, Clone, Copy)]
+pub enum ActionBinding {
+  Key(BevyKeyCode),
+  Mouse(BevyMouseButton),
+  /// Button of any connected gamepad
+  Gamepad(BevyGamepadButton),
+}
+
+#[derive(Clone, Copy)]
+enum ButtonQuery {
+  Pressed,
+  JustPressed,
+  JustReleased,
+}
+
+impl ButtonQuery {
+  fn input<T: Copy + Eq + Hash + Send + Sync>(self, input: &ButtonInput<T>, button: T) -> bool {
+    match self {
+      Self::Pressed => input.pressed(button),
+      Self::JustPressed => input.just_pressed(button),
+      Self::JustReleased => input.just_released(button),
+    }
+  }
+
+  fn gamepad(self, gamepad: &Gamepad, button: BevyGamepadButton) -> bool {
+    match self {
+      Self::Pressed => gamepad.pressed(button),
+      Self::JustPressed => gamepad.just_pressed(button),
+      Self::JustReleased => gamepad.just_released(button),
+    }
+  }
+}
+
+/// Returns true if any of the bindings of the action matches the query
+fn query_action(action: &str, query: ButtonQuery) -> bool {
+  use_world(|world| {
+    let Some(bindings) = world.resource::<Actions>().0.get(action).cloned() else {
+      return false;
+    };
+
+    bindings.into_iter().any(|binding| match binding {
+      ActionBinding::Key(key) => query.input(world.resource::<ButtonInput<BevyKeyCode>>(), key),
+      ActionBinding::Mouse(button) => {
+        query.input(world.resource::<ButtonInput<BevyMouseButton>>(), button)
+      }
+      ActionBinding::Gamepad(button) => world
+        .query::<&Gamepad>()
+        .iter(world)
+        .any(|gamepad| query.gamepad(gamepad, button)),
+    })
+  })
+}
+
+pub fn action_pressed(action: &str) -> bool {
+  query_action(action, ButtonQuery::Pressed)
+}
+
+pub fn action_just_pressed(action: &str) -> bool {
+  query_action(action, ButtonQuery::JustPressed)
+}
+
+pub fn action_just_released(action: &str) -> bool {
+  query_action(action, ButtonQuery::JustReleased)
+}
+
+/// Keyboard events of the current frame, collected by [`FkPlugin`]
+#[derive(Resource, Default)]
+struct FrameKeyEvents(Vec<FrameKeyEvent>);
+
+struct FrameKeyEvent {
+  key_code: KeyCode,
+  state: ButtonState,
+  repeat: bool,
+  text: String,
+}
+
+fn collect_key_events(
+  mut events: EventReader<KeyboardInput>,
+  mut key_events: ResMut<FrameKeyEvents>,
+) {
+  key_events.0.clear();
+  for event in events.read() {
+    let (state, text) = match (&event.state, &event.logical_key) {
+      (BevyButtonState::Pressed, Key::Character(text)) => (ButtonState::Pressed, text.to_string()),
+      (BevyButtonState::Pressed, Key::Space) => (ButtonState::Pressed, " ".to_owned()),
+      (BevyButtonState::Pressed, _) => (ButtonState::Pressed, String::new()),
+      (BevyButtonState::Released, _) => (ButtonState::Released, String::new()),
+    };
+
+    key_events.0.push(FrameKeyEvent {
+      key_code: key_code_from_bevy(&event.key_code),
+      state,
+      repeat: event.repeat,
+      text,
+    });
+  }
+}
+
+/// Returned memory is valid until the next call (text of the events until the next frame)
+pub fn key_events() -> RawSlice<RawKeyEvent> {
+  use_world(|world| {
+    let events = &world.resource::<FrameKeyEvents>().0;
+    RAW_KEY_EVENTS.with_borrow_mut(|raw_events| {
+      raw_events.clear();
+      raw_events.extend(events.iter().map(|event| RawKeyEvent {
+        key_code: event.key_code,
+        state: event.state,
+        repeat: event.repeat,
+        text: event.text.as_str().into(),
+      }));
+      raw_events.as_slice().into()
+    })
+  })
+}
+
+pub fn frame_time() -> FrameTime {
+  use_world(|world| {
+    let time = world.resource::<Time>();
+    FrameTime {
+      delta_secs: time.delta_secs(),
+      elapsed_secs: time.elapsed_secs_f64(),
+      frame_count: world.resource::<FrameCount>().0,
+    }
+  })
+}
+
+pub fn set_fixed_timestep(seconds: f64) {
+  // bevy panics on zero, negative or not finite timestep
+  let timestep = match Duration::try_from_secs_f64(seconds) {
+    Ok(timestep) if !timestep.is_zero() => timestep,
+    _ => {
+      eprintln!("invalid fixed timestep: {seconds} seconds, it must be positive");
+      return;
+    }
+  };
+  use_world(|world| {
+    world.resource_mut::<Time<Fixed>>().set_timestep(timestep);
+  })
+}
+
 pub fn spawn_empty() -> Entity {
   use_world(|world| {
     let entity = world.spawn_empty().id();
@@ -177,10 +1352,73 @@ pub fn spawn_empty() -> Entity {
 pub fn despawn(entity: Entity) {
   use_world(|world| {
     let entity = entity_to_bevy(entity);
-    world.despawn(entity);
-    ENTITIES.with_borrow_mut(|entities| {
-      assert!(entities.remove(&entity));
-    });
+    // e.g. a child which was despawned together with its parent
+    if !ENTITIES.with_borrow(|entities| entities.contains(&entity)) {
+      eprintln!("entity {entity} is already despawned");
+      return;
+    }
+    despawn_recursive(world, entity);
+  })
+}
+
+/// Descendants spawned by the module are also removed from `ENTITIES`
+fn despawn_recursive(world: &mut World, entity: BevyEntity) {
+  ENTITIES.with_borrow_mut(|entities| {
+    let mut stack = vec![entity];
+    while let Some(entity) = stack.pop() {
+      entities.remove(&entity);
+      if let Some(children) = world.get::<Children>(entity) {
+        stack.extend(children.iter().copied());
+      }
+    }
+  });
+  // may already be despawned by bevy together with an untracked parent
+  // (e.g. a node of a scene or an audio player despawned after playback)
+  if let Ok(entity) = world.get_entity_mut(entity) {
+    entity.try_despawn_recursive();
+  }
+}
+
+pub fn spawn_spatial(transform: Transform) -> Entity {
+  use_world(|world| {
+    let entity = world.spawn((transform, Visibility::default())).id();
+    ENTITIES.with_borrow_mut(|entities| entities.insert(entity));
+    bevy_to_entity(entity)
+  })
+}
+
+pub fn set_parent(child: Entity, parent: Entity) {
+  use_world(|world| {
+    let (child, parent) = (entity_to_bevy(child), entity_to_bevy(parent));
+    if !world.entities().contains(parent) {
+      eprintln!("failed to set parent: parent {parent} does not exist");
+      return;
+    }
+    // bevy panics if the entity becomes its own parent, and a cycle breaks the hierarchy
+    let mut ancestor = Some(parent);
+    while let Some(entity) = ancestor {
+      if entity == child {
+        eprintln!("failed to set parent: {parent} is {child} or its descendant");
+        return;
+      }
+      ancestor = world.get::<Parent>(entity).map(Parent::get);
+    }
+
+    match world.get_entity_mut(child) {
+      Ok(mut child) => {
+        child.set_parent(parent);
+      }
+      Err(e) => eprintln!("failed to set parent: {e}"),
+    }
+  })
+}
+
+pub fn remove_parent(child: Entity) {
+  use_world(|world| match world.get_entity_mut(entity_to_bevy(child)) {
+    Ok(mut child) => {
+      child.remove_parent();
+    }
+    Err(e) => eprintln!("failed to remove parent: {e}"),
   })
 }
 
@@ -203,40 +1441,80 @@ pub fn finish_mut_entity_transform(entity: Entity, mutated: &StableTransform) {
   })
 }
 
+/// Keeps the handle alive until [`drop_asset`] is called with the returned index
+fn insert_asset_handle<A: Asset>(handle: Handle<A>) -> BevyRawAssetIndex {
+  let Handle::Strong(handle) = handle else {
+    unreachable!();
+  };
+
+  let index = NEXT_ASSET_INDEX.get();
+  NEXT_ASSET_INDEX.set(index + 1);
+  ASSET_HANDLES.with_borrow_mut(|handles| {
+    handles.insert(index, handle);
+  });
+  index
+}
+
+/// Fails if the asset is dropped or has a different type
+fn asset_handle<A: Asset>(index: BevyRawAssetIndex) -> Result<Handle<A>, String> {
+  let handle = ASSET_HANDLES
+    .with_borrow(|handles| handles.get(&index).cloned())
+    .ok_or_else(|| format!("asset {index} is dropped"))?;
+  UntypedHandle::Strong(handle)
+    .try_typed::<A>()
+    .map_err(|e| format!("asset {index} has a different type: {e}"))
+}
+
 pub fn load_audio_asset(path: &str) -> BevyRawAssetIndex {
   use_world(|world| {
     let handle: Handle<AudioSource> = world.load_asset(path);
+    insert_asset_handle(handle)
+  })
+}
 
-    let AssetId::Index {
-      index: handle_index,
-      marker: _,
-    } = handle.id()
-    else {
-      unreachable!();
+pub fn load_scene_asset(path: &str) -> BevyRawAssetIndex {
+  use_world(|world| {
+    let path = AssetPath::parse(path).into_owned();
+    let path = if path.label().is_some() {
+      path
+    } else {
+      GltfAssetLabel::Scene(0).from_asset(path)
     };
-    let handle_index = handle_index.to_bits();
-
-    ASSET_HANDLES.with_borrow_mut({
-      // let handle = handle.clone();
-      let Handle::Strong(handle) = handle else {
-        unreachable!();
-      };
-      |handles| {
-        handles.insert(handle_index, handle);
+
+    let handle: Handle<Scene> = world.load_asset(path);
+    insert_asset_handle(handle)
+  })
+}
+
+pub fn spawn_scene(scene: BevyRawAssetIndex, transform: Transform) -> Entity {
+  use_world(|world| {
+    let mut entity = world.spawn(transform);
+    match asset_handle::<Scene>(scene) {
+      Ok(scene) => {
+        entity.insert(SceneRoot(scene));
       }
-    });
+      Err(e) => eprintln!("spawning entity without scene: {e}"),
+    }
+    let entity = entity.id();
+    ENTITIES.with_borrow_mut(|entities| entities.insert(entity));
 
-    handle_index
+    bevy_to_entity(entity)
   })
 }
 
 pub fn play_audio(asset: BevyRawAssetIndex) -> Entity {
   use_world(|world| {
-    let handle = ASSET_HANDLES.with_borrow(|handles| handles.get(&asset).cloned().unwrap());
+    let handle = match asset_handle::<AudioSource>(asset) {
+      Ok(handle) => handle,
+      Err(e) => {
+        eprintln!("failed to play audio: {e}");
+        return bevy_to_entity(BevyEntity::PLACEHOLDER);
+      }
+    };
 
     let entity = world
       .spawn((
-        AudioPlayer(Handle::<AudioSource>::Strong(handle)),
+        AudioPlayer(handle),
         PlaybackSettings::DESPAWN.with_volume(Volume::new(0.5)),
       ))
       .id();
@@ -252,20 +1530,152 @@ pub fn drop_asset(index: BevyRawAssetIndex) {
 }
 
 pub fn clear_world(world: &mut World) {
+  world.insert_resource(BevyAmbientLight::default());
+  world.insert_resource(ClearColor::default());
+
   ASSET_HANDLES.with_borrow_mut(|handles| {
     handles.clear();
   });
-  ENTITIES.with_borrow_mut(|entities| {
-    for entity in entities.drain() {
-      world.despawn(entity);
+  let entities = ENTITIES.with_borrow(|entities| entities.iter().copied().collect::<Vec<_>>());
+  for entity in entities {
+    // already despawned if it's a descendant of another entity
+    let tracked = ENTITIES.with_borrow(|entities| entities.contains(&entity));
+    if tracked {
+      despawn_recursive(world, entity);
     }
-  });
+  }
 }
 
-/// See [`core::mem::discriminant`]
-pub fn bevy_key_code_enum_discriminant(key_code: &BevyKeyCode) -> u32 {
-  // SAFETY: Because `KeyCode` is marked `repr(u32)`, its layout is a `repr(C)` `union`
-  // between `repr(C)` structs, each of which has the `u32` discriminant as its first
-  // field, so we can read the discriminant without offsetting the pointer.
-  unsafe { *<*const _>::from(key_code).cast::<u32>() }
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bevy::{
+    asset::{AssetApp, AssetPlugin},
+    input::InputPlugin,
+    MinimalPlugins,
+  };
+
+  /// Calls functions of this crate with the world of the app
+  fn with_world<R>(app: &mut App, use_: impl FnOnce() -> R) -> R {
+    let restore_world = take_world(app.world_mut());
+    let result = use_();
+    restore_world(app.world_mut());
+    result
+  }
+
+  #[test]
+  fn gamepad_queries() {
+    let mut app = App::new();
+
+    let mut gamepad = Gamepad::default();
+    gamepad.digital_mut().press(BevyGamepadButton::South);
+    gamepad.analog_mut().set(BevyGamepadAxis::LeftStickX, 0.75);
+    let gamepad = bevy_to_entity(app.world_mut().spawn(gamepad).id());
+
+    with_world(&mut app, || {
+      // SAFETY: returned memory is valid until the next call
+      assert_eq!(unsafe { gamepads().to_vec() }, [gamepad.0]);
+      assert!(gamepad_pressed(gamepad, GamepadButton::South));
+      assert!(gamepad_just_pressed(gamepad, GamepadButton::South));
+      assert!(!gamepad_pressed(gamepad, GamepadButton::East));
+      assert_eq!(gamepad_axis(gamepad, GamepadAxis::LeftStickX), 0.75);
+      assert_eq!(gamepad_axis(gamepad, GamepadAxis::LeftStickY), 0.0);
+    });
+
+    app.world_mut().despawn(entity_to_bevy(gamepad));
+
+    with_world(&mut app, || {
+      // SAFETY: returned memory is valid until the next call
+      assert!(unsafe { gamepads().to_vec() }.is_empty());
+      assert!(!gamepad_pressed(gamepad, GamepadButton::South));
+      assert!(!gamepad_just_pressed(gamepad, GamepadButton::South));
+      assert_eq!(gamepad_axis(gamepad, GamepadAxis::LeftStickX), 0.0);
+    });
+  }
+
+  #[test]
+  fn despawn_with_descendants() {
+    let mut app = App::new();
+
+    let (parent, child) = with_world(&mut app, || {
+      let parent = spawn_spatial(Transform::IDENTITY);
+      let child = spawn_spatial(Transform::IDENTITY);
+      set_parent(child, parent);
+      // cycles are rejected
+      set_parent(parent, child);
+      set_parent(parent, parent);
+
+      despawn(parent);
+      ENTITIES.with_borrow(|entities| {
+        assert!(!entities.contains(&entity_to_bevy(parent)));
+        assert!(!entities.contains(&entity_to_bevy(child)));
+      });
+      // already despawned together with the parent
+      despawn(child);
+      set_parent(child, parent);
+      remove_parent(child);
+      (parent, child)
+    });
+
+    assert!(app.world().get_entity(entity_to_bevy(parent)).is_err());
+    assert!(app.world().get_entity(entity_to_bevy(child)).is_err());
+  }
+
+  #[test]
+  fn key_code_round_trip() {
+    for &key_code in KEY_CODES {
+      assert!(
+        key_code_from_bevy(&key_code_to_bevy(key_code)) == key_code,
+        "key code {} changed after the round trip",
+        key_code as u32
+      );
+    }
+  }
+
+  #[test]
+  fn asset_cache_shares_and_prunes_assets() {
+    let mut app = App::new();
+    app
+      .add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        InputPlugin,
+        FkPlugin,
+      ))
+      .init_asset::<Mesh>()
+      .init_asset::<StandardMaterial>();
+
+    const SEGMENTS: u64 = 5;
+    let segments = with_world(&mut app, || {
+      (0..SEGMENTS)
+        .map(|_| {
+          spawn_color_mesh(
+            Transform::IDENTITY,
+            &Shape::Sphere(0.5),
+            Rgba(0, 255, 0, 255),
+          )
+        })
+        .collect::<Vec<_>>()
+    });
+
+    let cache = app.world().resource::<AssetCache>();
+    assert_eq!(cache.meshes(), 1);
+    assert_eq!(cache.color_materials(), 1);
+    // a mesh and a material per segment
+    assert_eq!(cache.misses(), 2);
+    assert_eq!(cache.hits(), 2 * (SEGMENTS - 1));
+
+    with_world(&mut app, || {
+      for segment in segments {
+        despawn(segment);
+      }
+    });
+    app.update();
+
+    let cache = app.world().resource::<AssetCache>();
+    assert_eq!(cache.meshes(), 0);
+    assert_eq!(cache.color_materials(), 0);
+    assert_eq!(app.world().resource::<Assets<Mesh>>().len(), 0);
+    assert_eq!(app.world().resource::<Assets<StandardMaterial>>().len(), 0);
+  }
 }
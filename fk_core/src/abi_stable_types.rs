@@ -70,6 +70,32 @@ impl From<&str> for Str {
   }
 }
 
+/// FFI-safe `Option<T>`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub enum StableOption<T> {
+  None,
+  Some(T),
+}
+
+impl<T> From<Option<T>> for StableOption<T> {
+  fn from(value: Option<T>) -> Self {
+    match value {
+      Some(value) => Self::Some(value),
+      None => Self::None,
+    }
+  }
+}
+
+impl<T> From<StableOption<T>> for Option<T> {
+  fn from(value: StableOption<T>) -> Self {
+    match value {
+      StableOption::Some(value) => Some(value),
+      StableOption::None => None,
+    }
+  }
+}
+
 pub struct OwnedStr {
   ptr: *mut u8,
   len: usize,
@@ -1,5 +1,13 @@
-use bevy_math::{Quat, Vec3};
+pub mod abi_stable_types;
+
+// re-exported for the interface in shared crate (host doesn't depend on bevy_math directly)
+pub use bevy_math::{Quat, Vec2, Vec3};
 use bevy_transform::components::Transform;
+use abi_stable_types::{RawSlice, StableOption, Str};
+
+// sources of the types crossing the module boundary, included in the interface hash of shared crate
+pub const LIB_SOURCE: &str = include_str!("lib.rs");
+pub const ABI_STABLE_TYPES_SOURCE: &str = include_str!("abi_stable_types.rs");
 
 pub fn def<T: Default>() -> T {
   Default::default()
@@ -10,18 +18,108 @@ pub type RawEntity = u64;
 #[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
 pub struct Entity(pub RawEntity);
 
-/// See <https://docs.rs/bevy_asset/0.15.3/bevy_asset/struct.AssetIndex.html>
+/// Index of the asset handle owned by the host on behalf of the module,
+/// unlike <https://docs.rs/bevy_asset/0.15.3/bevy_asset/struct.AssetIndex.html> it's unique across asset types
 pub type BevyRawAssetIndex = u64;
 
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Rgba(pub u8, pub u8, pub u8, pub u8);
 
 #[repr(C)]
 pub enum Shape {
   Cuboid(Vec3),
+  /// Width and height, facing up
   Plane(f32, f32),
+  /// Radius
   Sphere(f32),
+  /// Radius, sectors and stacks of the UV sphere (`Sphere` is an icosphere with fixed resolution)
+  UvSphere(f32, u32, u32),
+  /// Radius and height
+  Cylinder(f32, f32),
+  /// Radius and length of the middle part
+  Capsule(f32, f32),
+  /// Radius of the base and height
+  Cone(f32, f32),
+  /// Inner and outer radius
+  Torus(f32, f32),
+  /// Radius, facing forward (towards +Z)
+  Circle(f32),
+  /// Width and height, facing forward (towards +Z)
+  Rectangle(f32, f32),
+}
+
+/// Vertex data of a custom mesh (triangle list)
+#[repr(C)]
+pub struct RawMesh {
+  pub positions: RawSlice<Vec3>,
+  /// Can be empty, then normals are computed (smooth if the mesh is indexed, flat otherwise)
+  pub normals: RawSlice<Vec3>,
+  /// Can be empty if the mesh is not textured
+  pub uvs: RawSlice<Vec2>,
+  /// Can be empty if the mesh is not indexed
+  pub indices: RawSlice<u32>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum ImageFormat {
+  /// 4 bytes per pixel, color in sRGB space
+  Rgba8Srgb,
+  /// 4 bytes per pixel, linear data (for example, normal map)
+  Rgba8,
+  /// 1 byte per pixel
+  R8,
+}
+
+/// Pixels of an image, row by row from the top left corner
+#[repr(C)]
+pub struct RawImage {
+  pub width: u32,
+  pub height: u32,
+  pub format: ImageFormat,
+  pub pixels: RawSlice<u8>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum AlphaMode {
+  Opaque,
+  /// Pixels with alpha below the threshold are discarded
+  Mask(f32),
+  Blend,
+}
+
+/// Physically based material, see <https://docs.rs/bevy/0.15.3/bevy/pbr/struct.StandardMaterial.html>
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Material {
+  pub base_color: Rgba,
+  /// Image multiplied by `base_color`
+  pub base_color_texture: StableOption<BevyRawAssetIndex>,
+  pub emissive: Rgba,
+  /// Multiplier of `emissive`, values above 1.0 make it glow with bloom
+  pub emissive_intensity: f32,
+  pub metallic: f32,
+  pub roughness: f32,
+  pub alpha_mode: AlphaMode,
+  /// Not affected by lights
+  pub unlit: bool,
+}
+
+impl Default for Material {
+  fn default() -> Self {
+    Self {
+      base_color: Rgba(255, 255, 255, 255),
+      base_color_texture: StableOption::None,
+      emissive: Rgba(0, 0, 0, 255),
+      emissive_intensity: 1.0,
+      metallic: 0.0,
+      roughness: 0.5,
+      alpha_mode: AlphaMode::Opaque,
+      unlit: false,
+    }
+  }
 }
 
 #[repr(C)]
@@ -33,6 +131,161 @@ pub struct PointLight {
   pub color: Rgba,
 }
 
+/// Light shining in the forward direction of the entity (towards -Z)
+/// from infinitely far away, like the sun
+#[repr(C)]
+pub struct DirectionalLight {
+  /// In lux
+  pub illuminance: f32,
+  pub color: Rgba,
+  pub shadows_enabled: bool,
+  pub shadow_depth_bias: f32,
+  /// Must be positive (at most 4 are used), invalid cascades are replaced by the default ones
+  pub shadow_cascades: u32,
+  /// Distance from the camera which is covered by the first cascade
+  /// (must be greater than 0.1 if there are multiple cascades)
+  pub first_cascade_far_bound: f32,
+  /// Shadows are not rendered further from the camera (must be greater than 0.1)
+  pub max_shadow_distance: f32,
+}
+
+impl Default for DirectionalLight {
+  fn default() -> Self {
+    Self {
+      illuminance: 10_000.0,
+      color: Rgba(255, 255, 255, 255),
+      shadows_enabled: false,
+      shadow_depth_bias: 0.02,
+      shadow_cascades: 4,
+      first_cascade_far_bound: 5.0,
+      max_shadow_distance: 1000.0,
+    }
+  }
+}
+
+/// Cone of light shining in the forward direction of the entity (towards -Z)
+#[repr(C)]
+pub struct SpotLight {
+  /// In lumens
+  pub intensity: f32,
+  pub range: f32,
+  pub color: Rgba,
+  pub shadows_enabled: bool,
+  pub shadow_depth_bias: f32,
+  /// Angle in radians from the center of the cone where the light starts to fade out
+  pub inner_angle: f32,
+  /// Angle in radians from the center of the cone where the light is completely faded out
+  pub outer_angle: f32,
+}
+
+impl Default for SpotLight {
+  fn default() -> Self {
+    Self {
+      intensity: 1_000_000.0,
+      range: 20.0,
+      color: Rgba(255, 255, 255, 255),
+      shadows_enabled: false,
+      shadow_depth_bias: 0.02,
+      inner_angle: 0.0,
+      outer_angle: std::f32::consts::FRAC_PI_4,
+    }
+  }
+}
+
+/// Light which illuminates everything evenly
+#[repr(C)]
+pub struct AmbientLight {
+  pub color: Rgba,
+  /// In cd/m^2
+  pub brightness: f32,
+}
+
+impl Default for AmbientLight {
+  fn default() -> Self {
+    Self {
+      color: Rgba(255, 255, 255, 255),
+      brightness: 80.0,
+    }
+  }
+}
+
+/// Global settings of the rendered scene
+#[repr(C)]
+pub struct Environment {
+  /// Color of the background (cameras can override it)
+  pub clear_color: Rgba,
+}
+
+impl Default for Environment {
+  fn default() -> Self {
+    Self {
+      clear_color: Rgba(43, 44, 47, 255),
+    }
+  }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum Projection {
+  /// Vertical field of view in radians (must be between 0 and PI)
+  Perspective(f32),
+  /// Visible height in world units (width depends on the aspect ratio of the viewport)
+  Orthographic(f32),
+}
+
+/// Rectangle of the window in physical pixels from the top left corner
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Viewport {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Camera {
+  /// Invalid projection (with near and far) is replaced by the default one
+  pub projection: Projection,
+  /// Must be positive for perspective projection
+  pub near: f32,
+  /// Must be greater than near
+  pub far: f32,
+  /// Overrides the global clear color
+  pub clear_color: StableOption<Rgba>,
+  /// Whole window if it's none, clamped to the window
+  pub viewport: StableOption<Viewport>,
+  /// Cameras with higher order are rendered on top of lower ones
+  pub order: i32,
+}
+
+impl Default for Camera {
+  fn default() -> Self {
+    Self {
+      projection: Projection::Perspective(std::f32::consts::FRAC_PI_4),
+      near: 0.1,
+      far: 1000.0,
+      clear_color: StableOption::None,
+      viewport: StableOption::None,
+      order: 0,
+    }
+  }
+}
+
+/// Time of the current frame taken from bevy `Time` resource
+/// (inside of `FixedUpdate` schedule it's the fixed time)
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct FrameTime {
+  /// Seconds since the previous frame
+  pub delta_secs: f32,
+  /// Seconds since the startup of the host
+  pub elapsed_secs: f64,
+  /// Frames since the startup of the host (wraps on overflow)
+  pub frame_count: u32,
+}
+
 #[derive(Clone)]
 #[repr(C)]
 pub struct StableTransform {
@@ -61,7 +314,95 @@ impl From<Transform> for StableTransform {
   }
 }
 
-// copy-pasted from bevy
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonState {
+  Pressed,
+  Released,
+}
+
+/// FFI-safe keyboard event, see `Imports::key_events`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RawKeyEvent {
+  pub key_code: KeyCode,
+  pub state: ButtonState,
+  /// True if the event was generated by holding the key
+  pub repeat: bool,
+  /// Text produced by the pressed key (empty if the key doesn't produce text or was released)
+  pub text: Str,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+  Left,
+  Right,
+  Middle,
+  Back,
+  Forward,
+  Other(u16),
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseScrollUnit {
+  /// Lines (or rows/columns) to scroll, usually reported by mouse wheels
+  Line,
+  /// Pixels to scroll, usually reported by touchpads
+  Pixel,
+}
+
+/// Mouse scroll accumulated during the current frame
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MouseScroll {
+  pub unit: MouseScrollUnit,
+  pub delta: Vec2,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadButton {
+  /// The bottom action button of the action pad (i.e. PS: Cross, Xbox: A)
+  South,
+  /// The right action button of the action pad (i.e. PS: Circle, Xbox: B)
+  East,
+  /// The upper action button of the action pad (i.e. PS: Triangle, Xbox: Y)
+  North,
+  /// The left action button of the action pad (i.e. PS: Square, Xbox: X)
+  West,
+  C,
+  Z,
+  LeftTrigger,
+  LeftTrigger2,
+  RightTrigger,
+  RightTrigger2,
+  Select,
+  Start,
+  Mode,
+  LeftThumb,
+  RightThumb,
+  DPadUp,
+  DPadDown,
+  DPadLeft,
+  DPadRight,
+  Other(u8),
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadAxis {
+  LeftStickX,
+  LeftStickY,
+  LeftZ,
+  RightStickX,
+  RightStickY,
+  RightZ,
+  Other(u8),
+}
+
+// copy-pasted from bevy (conversion between them is in fk)
 #[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 pub enum KeyCode {
@@ -0,0 +1,93 @@
+// minimal binary encoding of the state which is handed over
+// to the next module instance on live reload (see `Exports::save_state`)
+
+use fk_core::Entity;
+
+#[derive(Default)]
+pub struct Writer(Vec<u8>);
+
+impl Writer {
+  pub fn write(&mut self, value: &impl Blob) {
+    value.write(self);
+  }
+
+  pub fn bytes(&mut self, bytes: &[u8]) {
+    self.0.extend_from_slice(bytes);
+  }
+
+  pub fn finish(self) -> Vec<u8> {
+    self.0
+  }
+}
+
+pub struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+  pub fn new(bytes: &'a [u8]) -> Self {
+    Self(bytes)
+  }
+
+  pub fn read<T: Blob>(&mut self) -> Option<T> {
+    T::read(self)
+  }
+
+  pub fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+    let (bytes, rest) = self.0.split_first_chunk::<N>()?;
+    self.0 = rest;
+    Some(*bytes)
+  }
+
+  /// Returns `None` if not all bytes were read,
+  /// which most likely means that layout of the state has changed
+  pub fn finish(self) -> Option<()> {
+    self.0.is_empty().then_some(())
+  }
+}
+
+pub trait Blob: Sized {
+  fn write(&self, writer: &mut Writer);
+  fn read(reader: &mut Reader) -> Option<Self>;
+}
+
+macro_rules! impl_blob_for_numbers {
+  ($($ty:ty),*) => {
+    $(
+      impl Blob for $ty {
+        fn write(&self, writer: &mut Writer) {
+          writer.bytes(&self.to_le_bytes());
+        }
+
+        fn read(reader: &mut Reader) -> Option<Self> {
+          reader.bytes().map(Self::from_le_bytes)
+        }
+      }
+    )*
+  };
+}
+
+impl_blob_for_numbers!(u8, u32, i32, u64, f32);
+
+impl Blob for Entity {
+  fn write(&self, writer: &mut Writer) {
+    writer.write(&self.0);
+  }
+
+  fn read(reader: &mut Reader) -> Option<Self> {
+    reader.read().map(Entity)
+  }
+}
+
+impl<T: Blob> Blob for Vec<T> {
+  fn write(&self, writer: &mut Writer) {
+    let len: u32 = self.len().try_into().unwrap();
+    writer.write(&len);
+    for item in self {
+      writer.write(item);
+    }
+  }
+
+  fn read(reader: &mut Reader) -> Option<Self> {
+    let len: u32 = reader.read()?;
+    (0..len).map(|_| reader.read()).collect()
+  }
+}
@@ -1,3 +1,4 @@
+use std::{backtrace::Backtrace, panic};
 use bevy_transform::components::Transform;
 pub use fk_core::*;
 
@@ -5,14 +6,162 @@ relib_interface::include_exports!();
 pub use gen_exports::ModuleExportsImpl;
 relib_interface::include_imports!(gen_imports);
 
+// name must match shared::INTERFACE_HASH_SYMBOL
+#[unsafe(no_mangle)]
+pub static SHARED_INTERFACE_HASH: u64 = shared::INTERFACE_HASH;
+
+/// Despawns the entity with its descendants (for example, nodes of a scene)
 pub fn despawn(entity: Entity) {
   unsafe { gen_imports::despawn(entity.0) }
 }
 
+/// Transform of the child becomes relative to the parent
+/// (parent should have a transform and visibility, see [`spawn_spatial`])
+pub fn set_parent(child: Entity, parent: Entity) {
+  unsafe { gen_imports::set_parent(child.0, parent.0) }
+}
+
+/// Transform of the entity becomes relative to the world
+#[allow(dead_code)]
+pub fn remove_parent(child: Entity) {
+  unsafe { gen_imports::remove_parent(child.0) }
+}
+
+#[allow(dead_code)]
 pub fn key_pressed(key: KeyCode) -> bool {
   unsafe { gen_imports::key_pressed(key) }
 }
 
+#[allow(dead_code)]
+pub fn key_just_pressed(key: KeyCode) -> bool {
+  unsafe { gen_imports::key_just_pressed(key) }
+}
+
+#[allow(dead_code)]
+pub fn key_just_released(key: KeyCode) -> bool {
+  unsafe { gen_imports::key_just_released(key) }
+}
+
+#[allow(dead_code)]
+pub struct KeyEvent {
+  pub key_code: KeyCode,
+  pub state: ButtonState,
+  /// True if the event was generated by holding the key
+  pub repeat: bool,
+  /// Text produced by the pressed key (empty if the key doesn't produce text or was released)
+  pub text: String,
+}
+
+/// Keyboard events of the current frame in order they happened
+#[allow(dead_code)]
+pub fn key_events() -> Vec<KeyEvent> {
+  let events = unsafe { gen_imports::key_events().into_slice() };
+  events
+    .iter()
+    .map(|event| KeyEvent {
+      key_code: event.key_code,
+      state: event.state,
+      repeat: event.repeat,
+      text: unsafe { event.text.to_string() },
+    })
+    .collect()
+}
+
+/// Seconds since the previous frame (or the fixed timestep inside of fixed update)
+pub fn delta_secs() -> f32 {
+  unsafe { gen_imports::frame_time() }.delta_secs
+}
+
+/// Seconds since the startup of the host (not reset on live reload)
+#[allow(dead_code)]
+pub fn elapsed_secs() -> f64 {
+  unsafe { gen_imports::frame_time() }.elapsed_secs
+}
+
+#[allow(dead_code)]
+pub fn frame_count() -> u32 {
+  unsafe { gen_imports::frame_time() }.frame_count
+}
+
+/// Sets how often `fixed_update` export is called (the host's `Time<Fixed>` timestep)
+pub fn set_fixed_timestep(seconds: f64) {
+  unsafe { gen_imports::set_fixed_timestep(seconds) }
+}
+
+#[allow(dead_code)]
+pub fn mouse_pressed(button: MouseButton) -> bool {
+  unsafe { gen_imports::mouse_pressed(button) }
+}
+
+#[allow(dead_code)]
+pub fn mouse_just_pressed(button: MouseButton) -> bool {
+  unsafe { gen_imports::mouse_just_pressed(button) }
+}
+
+#[allow(dead_code)]
+pub fn mouse_just_released(button: MouseButton) -> bool {
+  unsafe { gen_imports::mouse_just_released(button) }
+}
+
+/// Position of the cursor in the primary window (in logical pixels, from the top-left corner),
+/// none if the cursor is outside of the window
+#[allow(dead_code)]
+pub fn cursor_position() -> Option<Vec2> {
+  unsafe { gen_imports::cursor_position() }.into()
+}
+
+/// Mouse motion accumulated during the current frame (not affected by cursor acceleration)
+#[allow(dead_code)]
+pub fn mouse_motion() -> Vec2 {
+  unsafe { gen_imports::mouse_motion() }
+}
+
+#[allow(dead_code)]
+pub fn mouse_scroll() -> MouseScroll {
+  unsafe { gen_imports::mouse_scroll() }
+}
+
+/// Connected gamepads
+pub fn gamepads() -> Vec<Entity> {
+  let gamepads = unsafe { gen_imports::gamepads().into_slice() };
+  gamepads.iter().copied().map(Entity).collect()
+}
+
+#[allow(dead_code)]
+pub fn gamepad_pressed(gamepad: Entity, button: GamepadButton) -> bool {
+  unsafe { gen_imports::gamepad_pressed(gamepad.0, button) }
+}
+
+#[allow(dead_code)]
+pub fn gamepad_just_pressed(gamepad: Entity, button: GamepadButton) -> bool {
+  unsafe { gen_imports::gamepad_just_pressed(gamepad.0, button) }
+}
+
+#[allow(dead_code)]
+pub fn gamepad_just_released(gamepad: Entity, button: GamepadButton) -> bool {
+  unsafe { gen_imports::gamepad_just_released(gamepad.0, button) }
+}
+
+/// Value of the axis in range from -1.0 to 1.0 (0.0 if the gamepad is disconnected)
+pub fn gamepad_axis(gamepad: Entity, axis: GamepadAxis) -> f32 {
+  unsafe { gen_imports::gamepad_axis(gamepad.0, axis) }
+}
+
+/// Returns false if the action is not bound (see actions file of the loader)
+#[allow(dead_code)]
+pub fn action_pressed(action: &str) -> bool {
+  unsafe { gen_imports::action_pressed(action.into()) }
+}
+
+pub fn action_just_pressed(action: &str) -> bool {
+  unsafe { gen_imports::action_just_pressed(action.into()) }
+}
+
+#[allow(dead_code)]
+pub fn action_just_released(action: &str) -> bool {
+  unsafe { gen_imports::action_just_released(action.into()) }
+}
+
 pub fn load_audio_asset(path: &str) -> AssetHandle {
   let index = unsafe { gen_imports::load_audio_asset(path.into()) };
   AssetHandle(index)
@@ -36,33 +185,246 @@ fn finish_mut_entity_transform(entity: Entity, mutated: Transform) {
 // TODO: change field back to private
 pub struct AssetHandle(pub BevyRawAssetIndex);
 
+impl AssetHandle {
+  /// Releases the handle without dropping the asset, see [`AssetHandle::from_raw`]
+  pub fn into_raw(self) -> BevyRawAssetIndex {
+    let index = self.0;
+    std::mem::forget(self);
+    index
+  }
+
+  /// Takes back ownership of the handle released by [`AssetHandle::into_raw`]
+  /// (for example, by the previous module instance before live reload)
+  pub fn from_raw(index: BevyRawAssetIndex) -> Self {
+    Self(index)
+  }
+}
+
 impl Drop for AssetHandle {
   fn drop(&mut self) {
     unsafe { gen_imports::drop_asset(self.0) }
   }
 }
 
+/// Vertex data of a custom mesh, see [`RawMesh`]
+#[derive(Default)]
+#[allow(dead_code)]
+pub struct MeshData {
+  pub positions: Vec<Vec3>,
+  pub normals: Vec<Vec3>,
+  pub uvs: Vec<Vec2>,
+  pub indices: Vec<u32>,
+}
+
+impl MeshData {
+  #[allow(dead_code)]
+  fn as_raw(&self) -> RawMesh {
+    RawMesh {
+      positions: self.positions.as_slice().into(),
+      normals: self.normals.as_slice().into(),
+      uvs: self.uvs.as_slice().into(),
+      indices: self.indices.as_slice().into(),
+    }
+  }
+}
+
+#[allow(dead_code)]
+pub struct MeshHandle(AssetHandle);
+
+/// If the data is invalid, the error is printed and the mesh is empty
+#[allow(dead_code)]
+pub fn create_mesh(data: &MeshData) -> MeshHandle {
+  let index = unsafe { gen_imports::create_mesh(&data.as_raw()) };
+  MeshHandle(AssetHandle(index))
+}
+
+/// Replaces data of the mesh, entities which use it are updated too.
+/// If the data is invalid, the error is printed and the mesh is left unchanged
+#[allow(dead_code)]
+pub fn update_mesh(mesh: &MeshHandle, data: &MeshData) {
+  unsafe { gen_imports::update_mesh(mesh.0.0, &data.as_raw()) }
+}
+
+#[allow(dead_code)]
+pub fn spawn_mesh(transform: Transform, mesh: &MeshHandle, color: Rgba) -> Entity {
+  let entity = unsafe { gen_imports::spawn_mesh(&transform.into(), mesh.0.0, color) };
+  Entity(entity)
+}
+
+pub struct ImageHandle(AssetHandle);
+
+/// Pixels are row by row from the top left corner, see [`ImageFormat`] for their size.
+/// If the size doesn't match the pixels, the error is printed and the image is a single white pixel
+pub fn create_image(width: u32, height: u32, format: ImageFormat, pixels: &[u8]) -> ImageHandle {
+  let image = RawImage {
+    width,
+    height,
+    format,
+    pixels: pixels.into(),
+  };
+  let index = unsafe { gen_imports::create_image(&image) };
+  ImageHandle(AssetHandle(index))
+}
+
+#[allow(dead_code)]
+pub fn load_image_asset(path: &str) -> ImageHandle {
+  let index = unsafe { gen_imports::load_image_asset(path.into()) };
+  ImageHandle(AssetHandle(index))
+}
+
+pub fn spawn_image_mesh(transform: Transform, shape: &Shape, image: &ImageHandle) -> Entity {
+  let entity = unsafe { gen_imports::spawn_image_mesh(&transform.into(), shape, image.0.0) };
+  Entity(entity)
+}
+
+pub struct MaterialHandle(AssetHandle);
+
+impl MaterialHandle {
+  /// See [`AssetHandle::into_raw`]
+  pub fn into_raw(self) -> BevyRawAssetIndex {
+    self.0.into_raw()
+  }
+
+  /// See [`AssetHandle::from_raw`]
+  pub fn from_raw(index: BevyRawAssetIndex) -> Self {
+    Self(AssetHandle::from_raw(index))
+  }
+}
+
+fn with_texture(material: &Material, texture: Option<&ImageHandle>) -> Material {
+  Material {
+    base_color_texture: texture.map(|texture| texture.0.0).into(),
+    ..*material
+  }
+}
+
+/// `Material::base_color_texture` is replaced by the texture
+pub fn create_material(material: &Material, texture: Option<&ImageHandle>) -> MaterialHandle {
+  let index = unsafe { gen_imports::create_material(&with_texture(material, texture)) };
+  MaterialHandle(AssetHandle(index))
+}
+
+/// Replaces data of the material, entities which use it are updated too
+#[allow(dead_code)]
+pub fn update_material(material: &MaterialHandle, data: &Material, texture: Option<&ImageHandle>) {
+  unsafe { gen_imports::update_material(material.0.0, &with_texture(data, texture)) }
+}
+
+pub fn spawn_material_mesh(
+  transform: Transform,
+  shape: &Shape,
+  material: &MaterialHandle,
+) -> Entity {
+  let entity = unsafe { gen_imports::spawn_material_mesh(&transform.into(), shape, material.0.0) };
+  Entity(entity)
+}
+
+/// Replaces material of the entity spawned with a mesh
+#[allow(dead_code)]
+pub fn set_entity_material(entity: Entity, material: &MaterialHandle) {
+  unsafe { gen_imports::set_entity_material(entity.0, material.0.0) }
+}
+
+#[allow(dead_code)]
+pub struct SceneHandle(AssetHandle);
+
+/// Path can select a scene of the glTF file (e.g. `models/food.glb#Scene1`), otherwise the first one is loaded
+#[allow(dead_code)]
+pub fn load_scene_asset(path: &str) -> SceneHandle {
+  let index = unsafe { gen_imports::load_scene_asset(path.into()) };
+  SceneHandle(AssetHandle(index))
+}
+
+/// Nodes of the scene are spawned as descendants of the returned entity when the scene is loaded
+#[allow(dead_code)]
+pub fn spawn_scene(scene: &SceneHandle, transform: Transform) -> Entity {
+  let entity = unsafe { gen_imports::spawn_scene(scene.0.0, &transform.into()) };
+  Entity(entity)
+}
+
 pub fn play_audio(audio: &AssetHandle) -> Entity {
   let entity = unsafe { gen_imports::play_audio(audio.0) };
   Entity(entity)
 }
 
-pub fn spawn_camera(transform: Transform) -> Entity {
-  let entity = unsafe { gen_imports::spawn_camera(&transform.into()) };
+pub fn spawn_camera(transform: Transform, camera: &Camera) -> Entity {
+  let entity = unsafe { gen_imports::spawn_camera(&transform.into(), camera) };
   Entity(entity)
 }
 
+/// Replaces parameters of the camera spawned by [`spawn_camera`]
+#[allow(dead_code)]
+pub fn set_camera(entity: Entity, camera: &Camera) {
+  unsafe { gen_imports::set_camera(entity.0, camera) }
+}
+
 pub fn spawn_color_mesh(transform: Transform, shape: &Shape, color: Rgba) -> Entity {
   let entity = unsafe { gen_imports::spawn_color_mesh(&transform.into(), shape, color) };
   Entity(entity)
 }
 
+#[allow(dead_code)]
 pub fn spawn_empty() -> Entity {
   let entity = unsafe { gen_imports::spawn_empty() };
   Entity(entity)
 }
 
+/// Spawns an entity without visuals which can be a parent of other entities
+pub fn spawn_spatial(transform: Transform) -> Entity {
+  let entity = unsafe { gen_imports::spawn_spatial(&transform.into()) };
+  Entity(entity)
+}
+
 pub fn spawn_point_light(transform: Transform, light: &PointLight) -> Entity {
   let entity = unsafe { gen_imports::spawn_point_light(&transform.into(), light) };
   Entity(entity)
 }
+
+#[allow(dead_code)]
+pub fn set_point_light(entity: Entity, light: &PointLight) {
+  unsafe { gen_imports::set_point_light(entity.0, light) }
+}
+
+#[allow(dead_code)]
+pub fn spawn_directional_light(transform: Transform, light: &DirectionalLight) -> Entity {
+  let entity = unsafe { gen_imports::spawn_directional_light(&transform.into(), light) };
+  Entity(entity)
+}
+
+#[allow(dead_code)]
+pub fn set_directional_light(entity: Entity, light: &DirectionalLight) {
+  unsafe { gen_imports::set_directional_light(entity.0, light) }
+}
+
+#[allow(dead_code)]
+pub fn spawn_spot_light(transform: Transform, light: &SpotLight) -> Entity {
+  let entity = unsafe { gen_imports::spawn_spot_light(&transform.into(), light) };
+  Entity(entity)
+}
+
+#[allow(dead_code)]
+pub fn set_spot_light(entity: Entity, light: &SpotLight) {
+  unsafe { gen_imports::set_spot_light(entity.0, light) }
+}
+
+/// Reset when the world is cleared
+#[allow(dead_code)]
+pub fn set_ambient_light(light: &AmbientLight) {
+  unsafe { gen_imports::set_ambient_light(light) }
+}
+
+/// Reset when the world is cleared
+#[allow(dead_code)]
+pub fn set_environment(environment: &Environment) {
+  unsafe { gen_imports::set_environment(environment) }
+}
+
+/// Reports panics to the host (with backtrace) instead of printing them to stderr,
+/// must be called before any other code of the module is executed
+pub fn set_panic_hook() {
+  panic::set_hook(Box::new(|info| {
+    let message = info.to_string();
+    let backtrace = Backtrace::force_capture().to_string();
+    unsafe { gen_imports::report_panic(message.as_str().into(), backtrace.as_str().into()) }
+  }));
+}
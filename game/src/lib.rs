@@ -1,67 +1,122 @@
 mod fk;
+mod blob;
 
-use std::{
-  cell::RefCell,
-  time::{Duration, Instant},
-};
+use std::{cell::RefCell, f32::consts::FRAC_PI_2};
 use bevy_transform::components::Transform;
 use bevy_math::prelude::*;
-use fk_core::{Entity, KeyCode, PointLight, Rgba, Shape};
+use fk_core::{Entity, GamepadAxis, ImageFormat, Material, PointLight, Rgba, Shape};
 use rand::Rng;
 use fk::{
-  AssetHandle, def, despawn, key_pressed, load_audio_asset, mut_entity_transform, play_audio,
-  spawn_camera, spawn_color_mesh, spawn_empty, spawn_point_light, ModuleExportsImpl,
+  action_just_pressed, create_image, create_material, AssetHandle, def, delta_secs, despawn,
+  gamepad_axis, gamepads, load_audio_asset, mut_entity_transform, play_audio, set_fixed_timestep,
+  set_panic_hook, set_parent, spawn_camera, spawn_color_mesh, spawn_image_mesh,
+  spawn_material_mesh, spawn_point_light, spawn_spatial, ImageHandle, MaterialHandle,
+  ModuleExportsImpl,
 };
-use shared::exports::Exports;
+use shared::{abi_stable_types::RawSlice, exports::Exports};
 use relib_module as _;
+use blob::{Blob, Reader, Writer};
 
 impl Exports for ModuleExportsImpl {
   fn setup() {
+    set_panic_hook();
+    set_fixed_timestep(FIXED_TIMESTEP_SECS);
     setup();
   }
 
   fn update() {
     update();
   }
+
+  fn fixed_update() {
+    STATE.with_borrow_mut(|state| {
+      fixed_update(state.as_mut().unwrap());
+    });
+  }
+
+  fn save_state() -> RawSlice<u8> {
+    save_state()
+  }
+
+  fn restore_state(state: RawSlice<u8>) -> bool {
+    set_panic_hook();
+    set_fixed_timestep(FIXED_TIMESTEP_SECS);
+    restore_state(unsafe { state.into_slice() })
+  }
 }
 
 thread_local! {
   static STATE: RefCell<Option<State>> = RefCell::new(None);
+  // bytes returned from save_state must stay valid until the module is unloaded
+  static SAVED_STATE: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
 }
 
 struct State {
-  last_update: Instant,
-  since_last_update: Duration,
-  since_last_fixed_update: Duration,
   occupied_cells: OccupiedCells,
   snakes: Vec<Snake>,
   food: Vec<Food>,
   food_sound: AssetHandle,
+  food_material: MaterialHandle,
+}
+
+// increase it when layout of the saved state changes
+const STATE_VERSION: u32 = 6;
+
+impl State {
+  fn save(self) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.write(&STATE_VERSION);
+    writer.write(&self.occupied_cells);
+    writer.write(&self.snakes);
+    writer.write(&self.food);
+    writer.write(&self.food_sound.into_raw());
+    writer.write(&self.food_material.into_raw());
+    writer.finish()
+  }
+
+  fn restore(bytes: &[u8]) -> Option<Self> {
+    let mut reader = Reader::new(bytes);
+    let version: u32 = reader.read()?;
+    if version != STATE_VERSION {
+      return None;
+    }
+    let occupied_cells = reader.read()?;
+    let snakes = reader.read()?;
+    let food = reader.read()?;
+    let food_sound = reader.read()?;
+    let food_material = reader.read()?;
+    reader.finish()?;
+
+    Some(State {
+      occupied_cells,
+      snakes,
+      food,
+      food_sound: AssetHandle::from_raw(food_sound),
+      food_material: MaterialHandle::from_raw(food_material),
+    })
+  }
 }
 
 const CELLS: u32 = 20;
 
+// how often the snakes move
+const FIXED_TIMESTEP_SECS: f64 = 0.2;
+
 fn setup() {
   let size = (CELLS + 2) as f32;
   let offset = size / 2.0;
 
-  // TODO:
   // the grid
-  // spawn_image_mesh(
-  //   Transform::from_xyz(-offset, 0.0, offset),
-  //   Shape::Plane(size, size),
-  //   grid_texture(),
-  // );
-  spawn_color_mesh(
+  spawn_image_mesh(
     Transform::from_xyz(-offset, 0.0, offset),
     &Shape::Plane(size, size),
-    // Rgba(80, 80, 80, 255),
-    Rgba(80, 80, 110, 255),
+    &grid_texture(),
   );
 
   spawn_camera(
     Transform::from_xyz(-offset + (-offset / 2.), size * 1.5, offset - (offset / 2.))
       .looking_at(Vec3::new(-offset, 0., offset), Vec3::X),
+    &def(),
   );
 
   spawn_point_light(
@@ -79,39 +134,47 @@ fn setup() {
   spawn_snake(&mut snakes, &mut occupied_cells, 3);
 
   let food_sound = load_audio_asset("sounds/smb_coin.wav");
+  let food_material = create_material(
+    &Material {
+      base_color: Rgba(255, 0, 0, 255),
+      emissive: Rgba(255, 30, 30, 255),
+      emissive_intensity: 4.0,
+      ..def()
+    },
+    None,
+  );
 
-  spawn_food(&mut food, &mut occupied_cells);
+  spawn_food(&mut food, &mut occupied_cells, &food_material);
 
   STATE.set(Some(State {
-    last_update: Instant::now(),
-    since_last_update: Duration::ZERO,
-    since_last_fixed_update: Duration::ZERO,
     occupied_cells,
     snakes,
     food,
     food_sound,
+    food_material,
   }));
 }
 
+fn save_state() -> RawSlice<u8> {
+  let state = STATE.take().unwrap();
+  SAVED_STATE.with_borrow_mut(|saved| {
+    *saved = state.save();
+    saved.as_slice().into()
+  })
+}
+
+fn restore_state(bytes: &[u8]) -> bool {
+  let Some(state) = State::restore(bytes) else {
+    return false;
+  };
+  STATE.set(Some(state));
+  true
+}
+
 fn update() {
   STATE.with_borrow_mut(|state| {
     let state = state.as_mut().unwrap();
-
     control_snake(&mut state.snakes);
-
-    let now = Instant::now();
-    let since_last_update = now.duration_since(state.last_update);
-    state.last_update = now;
-    state.since_last_update = since_last_update;
-
-    state.since_last_fixed_update += since_last_update;
-    if state.since_last_fixed_update < Duration::from_millis(200) {
-      return;
-    }
-
-    fixed_update(state);
-
-    state.since_last_fixed_update = Duration::ZERO;
   });
 }
 
@@ -121,9 +184,27 @@ fn fixed_update(state: &mut State) {
   animate_food(state);
 }
 
+/// One pixel per cell and walls around them
+fn grid_texture() -> ImageHandle {
+  const WIDTH: u32 = CELLS
+    // left and right walls
+    + 2;
+  const WALL: [u8; 4] = [255, 255, 255, 255];
+  const CELL: [u8; 4] = [80, 80, 110, 255];
+
+  let mut pixels = Vec::with_capacity((WIDTH * WIDTH * 4) as usize);
+  for y in 0..WIDTH {
+    for x in 0..WIDTH {
+      let is_wall = x == 0 || y == 0 || x == WIDTH - 1 || y == WIDTH - 1;
+      pixels.extend(if is_wall { WALL } else { CELL });
+    }
+  }
+  create_image(WIDTH, WIDTH, ImageFormat::Rgba8Srgb, &pixels)
+}
+
 fn animate_food(state: &mut State) {
   for food in &mut state.food {
-    let rotate_for = state.since_last_fixed_update.as_millis() as f32 / 1000.0;
+    let rotate_for = delta_secs();
     mut_entity_transform(food.entity, |transform| {
       transform.rotate_y(rotate_for);
     });
@@ -143,6 +224,7 @@ fn spawn_snake_part(
     Rgba(0, 255, 0, 255),
     // Rgba(255, 255, 0, 255),
   );
+  set_parent(entity, snake.entity);
 
   snake.parts.push(SnakePart {
     entity,
@@ -157,10 +239,11 @@ fn spawn_snake_part(
 
 fn spawn_snake(snakes: &mut Vec<Snake>, occupied: &mut OccupiedCells, len: u8) {
   let mut snake = Snake {
-    entity: spawn_empty(),
+    // parent of the parts
+    entity: spawn_spatial(Transform::IDENTITY),
     parts: vec![],
     direction: Direction::Right,
-    next_direction: Direction::Right,
+    queued_directions: vec![],
   };
 
   spawn_snake_part(
@@ -223,7 +306,7 @@ fn is_it_safe_to_there(what: DoWhat, pos: Pos, occupied: &OccupiedCells) -> bool
   true
 }
 
-fn spawn_food(food: &mut Vec<Food>, occupied: &mut OccupiedCells) {
+fn spawn_food(food: &mut Vec<Food>, occupied: &mut OccupiedCells, material: &MaterialHandle) {
   let cells = CELLS.try_into().unwrap();
 
   let pos = loop {
@@ -242,30 +325,16 @@ fn spawn_food(food: &mut Vec<Food>, occupied: &mut OccupiedCells) {
     by_whom: Who::Food,
   });
 
-  let entity = spawn_color_mesh(
-    place_at(pos).with_scale(Vec3::splat(0.5)),
-    &Shape::Cuboid(Vec3::splat(1.)),
-    Rgba(255, 0, 0, 255),
+  // standing ring, so that its rotation is visible
+  let entity = spawn_material_mesh(
+    place_at(pos)
+      .with_scale(Vec3::splat(0.5))
+      .with_rotation(Quat::from_rotation_x(FRAC_PI_2)),
+    &Shape::Torus(0.6, 1.),
+    material,
   );
 
-  let light = spawn_point_light(
-    {
-      let mut transform = place_at(pos);
-      transform.translation.y += 3.0;
-      transform.translation.x -= 0.15;
-      transform.translation.z += 0.15;
-      transform
-    },
-    &PointLight {
-      intensity: 220_000.,
-      range: 5.,
-      color: Rgba(255, 0, 0, 255),
-      shadows_enabled: false,
-      shadow_depth_bias: 0.,
-    },
-  );
-
-  food.push(Food { entity, pos, light });
+  food.push(Food { entity, pos });
 }
 
 fn deoccupy_cell(occupied_cells: &mut OccupiedCells, removed: Pos) {
@@ -280,10 +349,10 @@ struct Snake {
   entity: Entity,
   parts: Vec<SnakePart>,
 
-  // these states are needed here to prevent player killing themselves
-  // by changing direction two times before update (see control_snake)
   direction: Direction,
-  next_direction: Direction,
+  // directions pressed since the last movement, one is applied per fixed update
+  // (validated against the previous one, so the player can't turn back into themselves)
+  queued_directions: Vec<Direction>,
 }
 
 struct SnakePart {
@@ -300,11 +369,27 @@ enum Direction {
   Right,
 }
 
+impl Direction {
+  fn opposite(self) -> Self {
+    match self {
+      Direction::Up => Direction::Down,
+      Direction::Down => Direction::Up,
+      Direction::Left => Direction::Right,
+      Direction::Right => Direction::Left,
+    }
+  }
+}
+
+// more than two turns between movements are most likely accidental
+const MAX_QUEUED_DIRECTIONS: usize = 2;
+
+// tilt of the gamepad stick after which it turns the snake
+const STICK_THRESHOLD: f32 = 0.5;
+
 #[derive(Debug)]
 struct Food {
   entity: Entity,
   pos: Pos,
-  light: Entity,
 }
 
 // position is stored in signed integers to avoid
@@ -332,22 +417,170 @@ enum DoWhat {
   Spawn,
 }
 
+impl Blob for Snake {
+  fn write(&self, writer: &mut Writer) {
+    writer.write(&self.entity);
+    writer.write(&self.parts);
+    writer.write(&self.direction);
+    writer.write(&self.queued_directions);
+  }
+
+  fn read(reader: &mut Reader) -> Option<Self> {
+    Some(Self {
+      entity: reader.read()?,
+      parts: reader.read()?,
+      direction: reader.read()?,
+      queued_directions: reader.read()?,
+    })
+  }
+}
+
+impl Blob for SnakePart {
+  fn write(&self, writer: &mut Writer) {
+    writer.write(&self.entity);
+    writer.write(&self.direction);
+    writer.write(&self.pos);
+  }
+
+  fn read(reader: &mut Reader) -> Option<Self> {
+    Some(Self {
+      entity: reader.read()?,
+      direction: reader.read()?,
+      pos: reader.read()?,
+    })
+  }
+}
+
+impl Blob for Direction {
+  fn write(&self, writer: &mut Writer) {
+    let value: u8 = match self {
+      Direction::Up => 0,
+      Direction::Down => 1,
+      Direction::Left => 2,
+      Direction::Right => 3,
+    };
+    writer.write(&value);
+  }
+
+  fn read(reader: &mut Reader) -> Option<Self> {
+    Some(match reader.read::<u8>()? {
+      0 => Direction::Up,
+      1 => Direction::Down,
+      2 => Direction::Left,
+      3 => Direction::Right,
+      _ => return None,
+    })
+  }
+}
+
+impl Blob for Food {
+  fn write(&self, writer: &mut Writer) {
+    writer.write(&self.entity);
+    writer.write(&self.pos);
+  }
+
+  fn read(reader: &mut Reader) -> Option<Self> {
+    Some(Self {
+      entity: reader.read()?,
+      pos: reader.read()?,
+    })
+  }
+}
+
+impl Blob for Pos {
+  fn write(&self, writer: &mut Writer) {
+    writer.write(&self.x);
+    writer.write(&self.y);
+  }
+
+  fn read(reader: &mut Reader) -> Option<Self> {
+    Some(Self {
+      x: reader.read()?,
+      y: reader.read()?,
+    })
+  }
+}
+
+impl Blob for OccupiedCell {
+  fn write(&self, writer: &mut Writer) {
+    writer.write(&self.pos);
+    let by_whom: u8 = match self.by_whom {
+      Who::Snake => 0,
+      Who::Food => 1,
+    };
+    writer.write(&by_whom);
+  }
+
+  fn read(reader: &mut Reader) -> Option<Self> {
+    Some(Self {
+      pos: reader.read()?,
+      by_whom: match reader.read::<u8>()? {
+        0 => Who::Snake,
+        1 => Who::Food,
+        _ => return None,
+      },
+    })
+  }
+}
+
 fn control_snake(snakes: &mut [Snake]) {
   let Some(snake) = snakes.first_mut() else {
     return;
   };
 
-  if key_pressed(KeyCode::ArrowUp) && snake.direction != Direction::Down {
-    snake.next_direction = Direction::Up;
+  // bindings of the actions are in assets/actions.toml
+  for (action, direction) in [
+    ("turn_up", Direction::Up),
+    ("turn_down", Direction::Down),
+    ("turn_right", Direction::Right),
+    ("turn_left", Direction::Left),
+  ] {
+    if action_just_pressed(action) {
+      queue_direction(snake, direction);
+    }
   }
-  if key_pressed(KeyCode::ArrowDown) && snake.direction != Direction::Up {
-    snake.next_direction = Direction::Down;
+
+  for gamepad in gamepads() {
+    if let Some(direction) = stick_direction(gamepad) {
+      queue_direction(snake, direction);
+    }
   }
-  if key_pressed(KeyCode::ArrowRight) && snake.direction != Direction::Left {
-    snake.next_direction = Direction::Right;
+}
+
+/// Direction in which the left stick of the gamepad is tilted (if it's tilted enough)
+fn stick_direction(gamepad: Entity) -> Option<Direction> {
+  let x = gamepad_axis(gamepad, GamepadAxis::LeftStickX);
+  let y = gamepad_axis(gamepad, GamepadAxis::LeftStickY);
+  if x.abs().max(y.abs()) < STICK_THRESHOLD {
+    return None;
   }
-  if key_pressed(KeyCode::ArrowLeft) && snake.direction != Direction::Right {
-    snake.next_direction = Direction::Left;
+
+  let direction = if x.abs() > y.abs() {
+    if x > 0.0 {
+      Direction::Right
+    } else {
+      Direction::Left
+    }
+  } else if y > 0.0 {
+    Direction::Up
+  } else {
+    Direction::Down
+  };
+  Some(direction)
+}
+
+// holding a key or a stick doesn't flood the queue since the same direction is ignored
+fn queue_direction(snake: &mut Snake, direction: Direction) {
+  let prev_direction = snake
+    .queued_directions
+    .last()
+    .copied()
+    .unwrap_or(snake.direction);
+  if direction == prev_direction || direction == prev_direction.opposite() {
+    return;
+  }
+  if snake.queued_directions.len() < MAX_QUEUED_DIRECTIONS {
+    snake.queued_directions.push(direction);
   }
 }
 
@@ -358,8 +591,10 @@ fn process_snake_movement(state: &mut State) {
     let mut next_positions = vec![];
 
     {
-      let next_direction = snake.next_direction;
-      snake.direction = next_direction;
+      if !snake.queued_directions.is_empty() {
+        snake.direction = snake.queued_directions.remove(0);
+      }
+      let next_direction = snake.direction;
       let head = &mut snake.parts[0];
       head.direction = next_direction;
 
@@ -423,10 +658,8 @@ fn process_snake_movement(state: &mut State) {
 
     for (entity, next_pos, direction) in next_positions {
       if !is_it_safe_to_there(DoWhat::Move, next_pos, &state.occupied_cells) {
+        // parts are despawned with it
         despawn(snake.entity);
-        for part in &snake.parts {
-          despawn(part.entity);
-        }
         snakes_to_remove.push(snake.entity);
 
         break;
@@ -480,12 +713,15 @@ fn process_snake_food(state: &mut State) {
     if let Some(idx) = despawn_food {
       let food = state.food.swap_remove(idx);
       despawn(food.entity);
-      despawn(food.light);
       deoccupy_cell(&mut state.occupied_cells, food.pos);
 
       play_audio(&state.food_sound);
 
-      spawn_food(&mut state.food, &mut state.occupied_cells);
+      spawn_food(
+        &mut state.food,
+        &mut state.occupied_cells,
+        &state.food_material,
+      );
 
       {
         let tail = snake.parts.last().unwrap();
@@ -0,0 +1,116 @@
+use std::{
+  collections::HashMap,
+  error::Error,
+  fs,
+  path::{Path, PathBuf},
+  sync::mpsc::{channel, Sender},
+};
+use bevy::input::{gamepad::GamepadButton, keyboard::KeyCode, mouse::MouseButton};
+use fk::{ActionBinding, Actions};
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode};
+use serde::Deserialize;
+use crate::live_reload::DEBOUNCE_TIMEOUT;
+
+type AnyErrorResult<T = ()> = Result<T, Box<dyn Error>>;
+
+/// Actions file shipped with the game, used until the configured one is loaded
+const DEFAULT_ACTIONS: &str = include_str!("../../assets/actions.toml");
+
+pub enum ActionsMessage {
+  Loaded(Actions),
+  /// Previous actions are kept
+  LoadError(String),
+}
+
+/// Bindings of one action in the actions file, names of the buttons are the same as in bevy:
+/// ```toml
+/// [turn_up]
+/// keys = ["ArrowUp", "KeyW"]
+/// mouse = ["Left"]
+/// gamepad = ["DPadUp"]
+/// ```
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ActionBindings {
+  keys: Vec<KeyCode>,
+  mouse: Vec<MouseButton>,
+  gamepad: Vec<GamepadButton>,
+}
+
+pub fn default_actions() -> Actions {
+  parse_actions(DEFAULT_ACTIONS).unwrap()
+}
+
+/// Sends actions loaded from the file and then sends them again on every change of the file
+pub fn run_loop(sender: Sender<ActionsMessage>, path: PathBuf) {
+  if let Err(e) = run_watcher(&sender, &path) {
+    let e = format!("stopped watching {}: {e}", path.display());
+    eprintln!("{e}");
+    sender.send(ActionsMessage::LoadError(e)).unwrap();
+  }
+}
+
+fn run_watcher(sender: &Sender<ActionsMessage>, path: &Path) -> AnyErrorResult {
+  let (fs_sender, fs_receiver) = channel();
+  let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, fs_sender)?;
+  // watching the directory since editors often replace the file instead of writing to it
+  let dir = match path.parent() {
+    // parent of a bare file name is empty
+    Some(dir) if dir.as_os_str().is_empty() => Path::new("."),
+    Some(dir) => dir,
+    None => return Err(format!("{} is not a file", path.display()).into()),
+  };
+  debouncer
+    .watcher()
+    .watch(dir, RecursiveMode::NonRecursive)?;
+
+  loop {
+    // previous actions are kept if the file is invalid
+    let message = match load_actions(path) {
+      Ok(actions) => {
+        println!("loaded actions from {}", path.display());
+        ActionsMessage::Loaded(actions)
+      }
+      Err(e) => {
+        let e = format!("failed to load actions from {}: {e}", path.display());
+        eprintln!("{e}");
+        ActionsMessage::LoadError(e)
+      }
+    };
+    sender.send(message).unwrap();
+
+    loop {
+      let events = fs_receiver.recv()??;
+      if events
+        .iter()
+        .any(|event| event.path.ends_with(path.file_name().unwrap()))
+      {
+        break;
+      }
+    }
+  }
+}
+
+fn load_actions(path: &Path) -> AnyErrorResult<Actions> {
+  let file = fs::read_to_string(path)?;
+  parse_actions(&file)
+}
+
+fn parse_actions(file: &str) -> AnyErrorResult<Actions> {
+  let file: HashMap<String, ActionBindings> = toml::from_str(file)?;
+
+  let actions = file
+    .into_iter()
+    .map(|(name, bindings)| {
+      let bindings = bindings
+        .keys
+        .into_iter()
+        .map(ActionBinding::Key)
+        .chain(bindings.mouse.into_iter().map(ActionBinding::Mouse))
+        .chain(bindings.gamepad.into_iter().map(ActionBinding::Gamepad))
+        .collect();
+      (name, bindings)
+    })
+    .collect();
+  Ok(Actions(actions))
+}
@@ -0,0 +1,122 @@
+use std::{fs, path::PathBuf};
+use bevy::window::Window;
+use clap::Parser;
+use serde::Deserialize;
+
+/// Builds the game module with cargo, loads it and reloads it on every change of its sources
+#[derive(Parser, Deserialize, Clone, Default)]
+#[command(version, about)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+  /// TOML file with any of the options below (values from the command line take precedence)
+  #[arg(long)]
+  #[serde(skip)]
+  config: Option<PathBuf>,
+
+  /// Cargo package of the module [default: game]
+  #[arg(long, short)]
+  package: Option<String>,
+
+  /// Cargo profile the module is built with [default: cargo's default profile]
+  #[arg(long)]
+  profile: Option<String>,
+
+  /// Directory of assets loaded by the module [default: assets]
+  #[arg(long)]
+  assets: Option<PathBuf>,
+
+  /// TOML file with bindings of actions, it's reloaded on every change [default: actions.toml in the assets directory]
+  #[arg(long)]
+  actions: Option<PathBuf>,
+
+  /// Unload the game when its build fails instead of keeping the last successful build running
+  #[arg(long)]
+  unload_on_build_failure: bool,
+
+  #[arg(long)]
+  window_title: Option<String>,
+
+  #[arg(long)]
+  window_width: Option<f32>,
+
+  #[arg(long)]
+  window_height: Option<f32>,
+
+  /// Extra arguments for `cargo build`, for example: `-- --features foo --release`
+  #[arg(last = true)]
+  cargo_args: Vec<String>,
+}
+
+impl Config {
+  /// Parses command line arguments and merges them with the config file (if it's specified)
+  pub fn load() -> Self {
+    let cli = Config::parse();
+    let Some(path) = &cli.config else {
+      return cli;
+    };
+
+    let file = fs::read_to_string(path)
+      .unwrap_or_else(|e| panic!("failed to read config file {}: {e}", path.display()));
+    let file: Config = toml::from_str(&file)
+      .unwrap_or_else(|e| panic!("failed to parse config file {}: {e}", path.display()));
+
+    Self {
+      config: cli.config,
+      package: cli.package.or(file.package),
+      profile: cli.profile.or(file.profile),
+      assets: cli.assets.or(file.assets),
+      actions: cli.actions.or(file.actions),
+      unload_on_build_failure: cli.unload_on_build_failure || file.unload_on_build_failure,
+      window_title: cli.window_title.or(file.window_title),
+      window_width: cli.window_width.or(file.window_width),
+      window_height: cli.window_height.or(file.window_height),
+      cargo_args: if cli.cargo_args.is_empty() {
+        file.cargo_args
+      } else {
+        cli.cargo_args
+      },
+    }
+  }
+
+  pub fn package(&self) -> &str {
+    self.package.as_deref().unwrap_or("game")
+  }
+
+  /// Arguments for `cargo build` (except the package and message format)
+  pub fn cargo_build_args(&self) -> Vec<&str> {
+    let mut args = vec![];
+    if let Some(profile) = &self.profile {
+      args.extend(["--profile", profile]);
+    }
+    args.extend(self.cargo_args.iter().map(String::as_str));
+    args
+  }
+
+  pub fn assets(&self) -> PathBuf {
+    self.assets.clone().unwrap_or_else(|| "assets".into())
+  }
+
+  pub fn actions(&self) -> PathBuf {
+    self
+      .actions
+      .clone()
+      .unwrap_or_else(|| self.assets().join("actions.toml"))
+  }
+
+  pub fn unload_on_build_failure(&self) -> bool {
+    self.unload_on_build_failure
+  }
+
+  pub fn window(&self) -> Window {
+    let mut window = Window::default();
+    if let Some(title) = &self.window_title {
+      window.title = title.clone();
+    }
+    let (width, height) = (
+      self.window_width.unwrap_or(window.resolution.width()),
+      self.window_height.unwrap_or(window.resolution.height()),
+    );
+    window.resolution = (width, height).into();
+    window
+  }
+}
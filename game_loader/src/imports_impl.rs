@@ -1,8 +1,13 @@
 use fk_core::{
-  BevyRawAssetIndex, Entity, KeyCode, PointLight, RawEntity, Rgba, Shape,
-  StableTransform,
+  AmbientLight, BevyRawAssetIndex, Camera, DirectionalLight, Entity, Environment, FrameTime,
+  GamepadAxis, GamepadButton, KeyCode, Material, MouseButton, MouseScroll, PointLight, RawEntity,
+  RawImage, RawKeyEvent, RawMesh, Rgba, Shape, SpotLight, StableTransform, Vec2,
 };
-use shared::{abi_stable_types::Str, imports::Imports};
+use shared::{
+  abi_stable_types::{RawSlice, StableOption, Str},
+  imports::Imports,
+};
+use crate::{overlay::GamePanic, report_game_panic};
 
 relib_interface::include_exports!();
 pub use gen_exports::ModuleExports;
@@ -15,10 +20,94 @@ impl Imports for ModuleImportsImpl {
     fk::despawn(Entity(entity))
   }
 
+  fn set_parent(child: RawEntity, parent: RawEntity) {
+    fk::set_parent(Entity(child), Entity(parent))
+  }
+
+  fn remove_parent(child: RawEntity) {
+    fk::remove_parent(Entity(child))
+  }
+
   fn key_pressed(key_code: KeyCode) -> bool {
     fk::key_pressed(key_code)
   }
 
+  fn key_just_pressed(key_code: KeyCode) -> bool {
+    fk::key_just_pressed(key_code)
+  }
+
+  fn key_just_released(key_code: KeyCode) -> bool {
+    fk::key_just_released(key_code)
+  }
+
+  fn key_events() -> RawSlice<RawKeyEvent> {
+    fk::key_events()
+  }
+
+  fn mouse_pressed(button: MouseButton) -> bool {
+    fk::mouse_pressed(button)
+  }
+
+  fn mouse_just_pressed(button: MouseButton) -> bool {
+    fk::mouse_just_pressed(button)
+  }
+
+  fn mouse_just_released(button: MouseButton) -> bool {
+    fk::mouse_just_released(button)
+  }
+
+  fn cursor_position() -> StableOption<Vec2> {
+    fk::cursor_position().into()
+  }
+
+  fn mouse_motion() -> Vec2 {
+    fk::mouse_motion()
+  }
+
+  fn mouse_scroll() -> MouseScroll {
+    fk::mouse_scroll()
+  }
+
+  fn gamepads() -> RawSlice<RawEntity> {
+    fk::gamepads()
+  }
+
+  fn gamepad_pressed(gamepad: RawEntity, button: GamepadButton) -> bool {
+    fk::gamepad_pressed(Entity(gamepad), button)
+  }
+
+  fn gamepad_just_pressed(gamepad: RawEntity, button: GamepadButton) -> bool {
+    fk::gamepad_just_pressed(Entity(gamepad), button)
+  }
+
+  fn gamepad_just_released(gamepad: RawEntity, button: GamepadButton) -> bool {
+    fk::gamepad_just_released(Entity(gamepad), button)
+  }
+
+  fn gamepad_axis(gamepad: RawEntity, axis: GamepadAxis) -> f32 {
+    fk::gamepad_axis(Entity(gamepad), axis)
+  }
+
+  fn action_pressed(action: Str) -> bool {
+    fk::action_pressed(unsafe { action.into_str() })
+  }
+
+  fn action_just_pressed(action: Str) -> bool {
+    fk::action_just_pressed(unsafe { action.into_str() })
+  }
+
+  fn action_just_released(action: Str) -> bool {
+    fk::action_just_released(unsafe { action.into_str() })
+  }
+
+  fn frame_time() -> FrameTime {
+    fk::frame_time()
+  }
+
+  fn set_fixed_timestep(seconds: f64) {
+    fk::set_fixed_timestep(seconds)
+  }
+
   fn drop_asset(index: BevyRawAssetIndex) {
     fk::drop_asset(index)
   }
@@ -35,12 +124,30 @@ impl Imports for ModuleImportsImpl {
     fk::finish_mut_entity_transform(Entity(entity), mutated)
   }
 
+  fn create_image(image: &RawImage) -> BevyRawAssetIndex {
+    fk::create_image(image.width, image.height, image.format, unsafe {
+      image.pixels.into_slice()
+    })
+  }
+
+  fn load_image_asset(path: Str) -> BevyRawAssetIndex {
+    fk::load_image_asset(unsafe { path.into_str() })
+  }
+
+  fn load_scene_asset(path: Str) -> BevyRawAssetIndex {
+    fk::load_scene_asset(unsafe { path.into_str() })
+  }
+
   fn play_audio(asset: BevyRawAssetIndex) -> RawEntity {
     fk::play_audio(asset).0
   }
 
-  fn spawn_camera(transform: &StableTransform) -> RawEntity {
-    fk::spawn_camera(transform.clone().into()).0
+  fn spawn_camera(transform: &StableTransform, camera: &Camera) -> RawEntity {
+    fk::spawn_camera(transform.clone().into(), camera).0
+  }
+
+  fn set_camera(entity: RawEntity, camera: &Camera) {
+    fk::set_camera(Entity(entity), camera)
   }
 
   fn spawn_color_mesh(transform: &StableTransform, shape: &Shape, color: Rgba) -> RawEntity {
@@ -51,7 +158,105 @@ impl Imports for ModuleImportsImpl {
     fk::spawn_empty().0
   }
 
+  fn spawn_spatial(transform: &StableTransform) -> RawEntity {
+    fk::spawn_spatial(transform.clone().into()).0
+  }
+
+  fn create_mesh(mesh: &RawMesh) -> BevyRawAssetIndex {
+    unsafe {
+      fk::create_mesh(
+        mesh.positions.into_slice(),
+        mesh.normals.into_slice(),
+        mesh.uvs.into_slice(),
+        mesh.indices.into_slice(),
+      )
+    }
+  }
+
+  fn update_mesh(mesh: BevyRawAssetIndex, data: &RawMesh) {
+    unsafe {
+      fk::update_mesh(
+        mesh,
+        data.positions.into_slice(),
+        data.normals.into_slice(),
+        data.uvs.into_slice(),
+        data.indices.into_slice(),
+      )
+    }
+  }
+
+  fn create_material(material: &Material) -> BevyRawAssetIndex {
+    fk::create_material(material)
+  }
+
+  fn update_material(material: BevyRawAssetIndex, data: &Material) {
+    fk::update_material(material, data)
+  }
+
+  fn spawn_material_mesh(
+    transform: &StableTransform,
+    shape: &Shape,
+    material: BevyRawAssetIndex,
+  ) -> RawEntity {
+    fk::spawn_material_mesh(transform.clone().into(), shape, material).0
+  }
+
+  fn set_entity_material(entity: RawEntity, material: BevyRawAssetIndex) {
+    fk::set_entity_material(Entity(entity), material)
+  }
+
+  fn spawn_scene(scene: BevyRawAssetIndex, transform: &StableTransform) -> RawEntity {
+    fk::spawn_scene(scene, transform.clone().into()).0
+  }
+
+  fn spawn_image_mesh(
+    transform: &StableTransform,
+    shape: &Shape,
+    image: BevyRawAssetIndex,
+  ) -> RawEntity {
+    fk::spawn_image_mesh(transform.clone().into(), shape, image).0
+  }
+
+  fn spawn_mesh(transform: &StableTransform, mesh: BevyRawAssetIndex, color: Rgba) -> RawEntity {
+    fk::spawn_mesh(transform.clone().into(), mesh, color).0
+  }
+
   fn spawn_point_light(transform: &StableTransform, light: &PointLight) -> RawEntity {
     fk::spawn_point_light(transform.clone().into(), light).0
   }
+
+  fn set_point_light(entity: RawEntity, light: &PointLight) {
+    fk::set_point_light(Entity(entity), light)
+  }
+
+  fn spawn_directional_light(transform: &StableTransform, light: &DirectionalLight) -> RawEntity {
+    fk::spawn_directional_light(transform.clone().into(), light).0
+  }
+
+  fn set_directional_light(entity: RawEntity, light: &DirectionalLight) {
+    fk::set_directional_light(Entity(entity), light)
+  }
+
+  fn spawn_spot_light(transform: &StableTransform, light: &SpotLight) -> RawEntity {
+    fk::spawn_spot_light(transform.clone().into(), light).0
+  }
+
+  fn set_spot_light(entity: RawEntity, light: &SpotLight) {
+    fk::set_spot_light(Entity(entity), light)
+  }
+
+  fn set_ambient_light(light: &AmbientLight) {
+    fk::set_ambient_light(light)
+  }
+
+  fn set_environment(environment: &Environment) {
+    fk::set_environment(environment)
+  }
+
+  fn report_panic(message: Str, backtrace: Str) {
+    report_game_panic(GamePanic {
+      message: unsafe { message.to_string() },
+      backtrace: unsafe { backtrace.to_string() },
+    });
+  }
 }
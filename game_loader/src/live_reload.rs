@@ -1,63 +1,235 @@
-use std::{error::Error, process::Command, thread, time::Duration, sync::mpsc::Sender};
+use std::{
+  env::consts::DLL_EXTENSION,
+  error::Error,
+  fs,
+  path::{Path, PathBuf},
+  process::Command,
+  sync::mpsc::{channel, Sender},
+  time::Duration,
+};
+use cargo_metadata::{
+  diagnostic::{Diagnostic as CargoDiagnostic, DiagnosticLevel},
+  DependencyKind, Message, MetadataCommand,
+};
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode};
+use crate::config::Config;
 
 type AnyErrorResult<T = ()> = Result<T, Box<dyn Error>>;
 
+// editors usually emit several events for one save (and cargo fmt on save even more)
+pub const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(100);
+
 pub enum LiveReloadMessage {
-  Success,
-  BuildFailure,
+  /// Contains path to a unique copy of the built module (see `copy_module`),
+  /// which should be removed after the module is unloaded
+  Success(PathBuf),
+  BuildFailure(Vec<Diagnostic>),
+}
+
+/// Error or warning emitted by the compiler (or cargo itself) while building the game module
+pub struct Diagnostic {
+  pub level: DiagnosticLevel,
+  /// Path to the file of the primary span, relative to the workspace
+  pub file: Option<String>,
+  pub line: usize,
+  pub column: usize,
+  pub message: String,
+  /// Text as it would have been printed to the terminal
+  pub rendered: String,
+}
+
+impl From<CargoDiagnostic> for Diagnostic {
+  fn from(value: CargoDiagnostic) -> Self {
+    let primary_span = value.spans.iter().find(|span| span.is_primary);
+
+    Self {
+      file: primary_span.map(|span| span.file_name.clone()),
+      line: primary_span.map(|span| span.line_start).unwrap_or_default(),
+      column: primary_span
+        .map(|span| span.column_start)
+        .unwrap_or_default(),
+      rendered: value.rendered.unwrap_or_else(|| value.message.clone()),
+      message: value.message,
+      level: value.level,
+    }
+  }
 }
 
-pub fn run_loop(sender: Sender<LiveReloadMessage>) {
-  if let Err(e) = run_host(sender) {
+pub fn run_loop(sender: Sender<LiveReloadMessage>, config: Config) {
+  if let Err(e) = run_host(sender, &config) {
     panic!("{e:#}");
   }
 }
 
-fn run_host(sender: Sender<LiveReloadMessage>) -> AnyErrorResult {
+fn run_host(sender: Sender<LiveReloadMessage>, config: &Config) -> AnyErrorResult {
+  let (fs_sender, fs_receiver) = channel();
+  let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, fs_sender)?;
+  for dir in watched_dirs(config.package())? {
+    debouncer.watcher().watch(&dir, RecursiveMode::Recursive)?;
+  }
+
+  let mut first_build = true;
   let mut build_failed_in_prev_iteration = false;
+  let mut module_copies = 0;
   loop {
-    match build_game_module()? {
-      BuildResult::Success => {
-        // inserting new line for more clear output of module after compilation failures or previous runs of the module
-        println!();
-        build_failed_in_prev_iteration = false;
+    match build_game_module(config)? {
+      BuildResult::Success { module, fresh } => {
+        // module can be fresh after failure if changes were reverted,
+        // but it still needs to be loaded again
+        if !fresh || first_build || build_failed_in_prev_iteration {
+          // inserting new line for more clear output of module after compilation failures or previous runs of the module
+          println!();
+          build_failed_in_prev_iteration = false;
 
-        sender.send(LiveReloadMessage::Success).unwrap();
-      }
-      BuildResult::Failure => {
-        if build_failed_in_prev_iteration {
-          continue;
+          let module = copy_module(&module, module_copies)?;
+          module_copies += 1;
+          sender.send(LiveReloadMessage::Success(module)).unwrap();
         }
+      }
+      // builds are only triggered by changes now, so diagnostics of every failure are sent
+      // (they may differ from the previous ones)
+      BuildResult::Failure(diagnostics) => {
         build_failed_in_prev_iteration = true;
         println!("failed to build the game");
+        for diagnostic in &diagnostics {
+          eprint!("{}", diagnostic.rendered);
+        }
+
+        sender
+          .send(LiveReloadMessage::BuildFailure(diagnostics))
+          .unwrap();
+      }
+    }
+    first_build = false;
+
+    // blocking until something is changed, then skipping
+    // events which were queued while the build was running
+    fs_receiver.recv()??;
+    while let Ok(events) = fs_receiver.try_recv() {
+      events?;
+    }
+  }
+}
 
-        sender.send(LiveReloadMessage::BuildFailure).unwrap();
+/// Copies the module to a uniquely named file (e.g. `live_reload/libgame.3.so` next to the artifact),
+/// so that cargo never overwrites the loaded file and the dynamic loader can't reuse the old image
+fn copy_module(artifact: &Path, index: u32) -> AnyErrorResult<PathBuf> {
+  let dir = artifact.parent().unwrap().join("live_reload");
+  if index == 0 {
+    // copies left after previous runs of the loader
+    if dir.exists() {
+      fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+  }
+
+  let stem = artifact.file_stem().unwrap().to_string_lossy();
+  let copy = dir.join(format!("{stem}.{index}.{DLL_EXTENSION}"));
+  fs::copy(artifact, &copy)?;
+  Ok(copy)
+}
+
+/// Directories of the package and all of its path dependencies (recursively)
+fn watched_dirs(package: &str) -> AnyErrorResult<Vec<PathBuf>> {
+  let metadata = MetadataCommand::new().exec()?;
+
+  let root = metadata
+    .packages
+    .iter()
+    .find(|p| p.name == package)
+    .ok_or_else(|| format!("package {package:?} is not found in cargo metadata"))?;
+
+  let mut dirs = vec![root.manifest_path.parent().unwrap().to_owned()];
+  let mut packages = vec![root];
+
+  while let Some(package) = packages.pop() {
+    let path_dependencies = package
+      .dependencies
+      .iter()
+      .filter(|dep| matches!(dep.kind, DependencyKind::Normal | DependencyKind::Build))
+      .filter_map(|dep| dep.path.as_ref());
+
+    for dir in path_dependencies {
+      if dirs.contains(dir) {
+        continue;
+      }
+      dirs.push(dir.clone());
+
+      let dependency = metadata
+        .packages
+        .iter()
+        .find(|p| p.manifest_path.parent() == Some(dir.as_path()));
+      if let Some(dependency) = dependency {
+        packages.push(dependency);
       }
-      BuildResult::NoChange => {}
     }
-    thread::sleep(Duration::from_millis(50));
   }
+
+  Ok(
+    dirs
+      .into_iter()
+      .map(|dir| dir.into_std_path_buf())
+      .collect(),
+  )
 }
 
-fn build_game_module() -> AnyErrorResult<BuildResult> {
+fn build_game_module(config: &Config) -> AnyErrorResult<BuildResult> {
+  let package = config.package();
   let output = Command::new("cargo")
-    .args(["build", "--package", "game"])
+    .args(["build", "--package", package, "--message-format=json"])
+    .args(config.cargo_build_args())
     .output()?;
   let stderr = String::from_utf8(output.stderr)?;
 
+  let mut diagnostics = vec![];
+  let mut module = None;
+  for message in Message::parse_stream(output.stdout.as_slice()) {
+    match message? {
+      Message::CompilerMessage(message) => {
+        diagnostics.push(message.message.into());
+      }
+      Message::CompilerArtifact(artifact) if artifact.target.is_cdylib() => {
+        let path = artifact
+          .filenames
+          .into_iter()
+          .find(|path| path.extension() == Some(DLL_EXTENSION));
+        module = path.map(|path| (path, artifact.fresh));
+      }
+      _ => {}
+    }
+  }
+
   if !output.status.success() {
-    return Ok(BuildResult::Failure);
+    // cargo failed before the compiler was invoked (e.g. invalid manifest)
+    if diagnostics.is_empty() {
+      diagnostics.push(Diagnostic {
+        level: DiagnosticLevel::Error,
+        file: None,
+        line: 0,
+        column: 0,
+        message: "cargo build failed".to_owned(),
+        rendered: stderr,
+      });
+    }
+
+    return Ok(BuildResult::Failure(diagnostics));
   }
 
-  Ok(if stderr.contains("Compiling") {
-    BuildResult::Success
-  } else {
-    BuildResult::NoChange
+  let Some((module, fresh)) = module else {
+    return Err(format!("cargo did not report cdylib artifact of {package:?} package").into());
+  };
+
+  Ok(BuildResult::Success {
+    module: module.into_std_path_buf(),
+    fresh,
   })
 }
 
 enum BuildResult {
-  Success,
-  Failure,
-  NoChange,
+  Success {
+    module: PathBuf,
+    /// True if cargo didn't rebuild the module
+    fresh: bool,
+  },
+  Failure(Vec<Diagnostic>),
 }
@@ -1,25 +1,35 @@
+mod actions;
+mod config;
 mod imports_impl;
 mod live_reload;
+mod overlay;
 
 use std::{
   cell::RefCell,
-  fs::canonicalize,
+  fs::{self, canonicalize},
+  path::{Path, PathBuf},
   sync::mpsc::{channel, Receiver},
 };
 
 use bevy::prelude::*;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use config::Config;
 use fk_core::def;
 use imports_impl::{init_imports, ModuleExports};
 use relib_host::{load_module, Module};
 use live_reload::LiveReloadMessage;
+use actions::ActionsMessage;
+use overlay::{ActionsLoadError, BuildDiagnostics, GameLoadError, GamePanic, StaleGame};
 
 fn main() {
-  load_game();
+  let config = Config::load();
 
   let (sender, receiver) = channel();
-  std::thread::spawn(|| {
-    live_reload::run_loop(sender);
+  std::thread::spawn({
+    let config = config.clone();
+    || {
+      live_reload::run_loop(sender, config);
+    }
   });
 
   thread_local! {
@@ -28,16 +38,72 @@ fn main() {
 
   LIVE_RELOAD_RECEIVER.replace(Some(receiver));
 
-  let game_update = |world: &mut World| {
+  let (actions_sender, actions_receiver) = channel();
+  std::thread::spawn({
+    let path = config.actions();
+    || {
+      actions::run_loop(actions_sender, path);
+    }
+  });
+
+  thread_local! {
+    static ACTIONS_RECEIVER: RefCell<Option<Receiver<ActionsMessage>>> = def();
+  }
+
+  ACTIONS_RECEIVER.replace(Some(actions_receiver));
+
+  let update_actions = |world: &mut World| {
+    let msg = ACTIONS_RECEIVER.with_borrow(|receiver| receiver.as_ref().unwrap().try_recv());
+    match msg {
+      Ok(ActionsMessage::Loaded(actions)) => {
+        world.insert_resource(actions);
+        world.remove_resource::<ActionsLoadError>();
+      }
+      Ok(ActionsMessage::LoadError(e)) => {
+        world.insert_resource(ActionsLoadError(e));
+      }
+      Err(_) => {}
+    }
+  };
+
+  let unload_on_build_failure = config.unload_on_build_failure();
+
+  let game_update = move |world: &mut World| {
     let msg = LIVE_RELOAD_RECEIVER.with_borrow(|receiver| receiver.as_ref().unwrap().try_recv());
     if let Ok(msg) = msg {
       match msg {
-        LiveReloadMessage::Success => {
-          unload_game();
-          load_game();
+        LiveReloadMessage::Success(module) => {
+          world.resource_mut::<BuildDiagnostics>().0.clear();
+          world.remove_resource::<GamePanic>();
+          world.remove_resource::<GameLoadError>();
+
+          match check_interface_hash(&module) {
+            Ok(()) => {
+              world.remove_resource::<StaleGame>();
+              unload_game(true);
+              load_game(&module);
+            }
+            Err(e) => {
+              eprintln!("{e}");
+              fs::remove_file(&module).unwrap();
+
+              let game_is_loaded = GAME_INSTANCE.with_borrow(|(instance, _)| instance.is_some());
+              if game_is_loaded {
+                world.insert_resource(StaleGame);
+              }
+              world.insert_resource(GameLoadError(e));
+            }
+          }
         }
-        LiveReloadMessage::BuildFailure => {
-          unload_game();
+        LiveReloadMessage::BuildFailure(diagnostics) => {
+          world.insert_resource(BuildDiagnostics(diagnostics));
+
+          let game_is_loaded = GAME_INSTANCE.with_borrow(|(instance, _)| instance.is_some());
+          if unload_on_build_failure {
+            unload_game(true);
+          } else if game_is_loaded {
+            world.insert_resource(StaleGame);
+          }
         }
       }
     }
@@ -56,28 +122,56 @@ fn main() {
     });
 
     if !setup_called {
-      fk::clear_world(world);
+      let saved_state = SAVED_GAME_STATE.take();
 
       let return_world = fk::take_world(world);
-      call_game_export(|game| unsafe {
-        game.setup().unwrap();
+      let restored = saved_state.map(|state| {
+        call_game_export(|game| unsafe { game.restore_state(state.as_slice().into()) })
       });
       return_world(world);
+
+      let restored = match restored {
+        Some(Some(restored)) => restored,
+        Some(None) => {
+          on_game_panic(world);
+          return;
+        }
+        None => false,
+      };
+
+      if !restored {
+        fk::clear_world(world);
+
+        let return_world = fk::take_world(world);
+        let returned = call_game_export(|game| unsafe { game.setup() });
+        return_world(world);
+
+        if returned.is_none() {
+          on_game_panic(world);
+          return;
+        }
+      }
     }
 
     let return_world = fk::take_world(world);
-    call_game_export(|game| unsafe {
-      game.update().unwrap();
-    });
+    let returned = call_game_export(|game| unsafe { game.update() });
     return_world(world);
+
+    if returned.is_none() {
+      on_game_panic(world);
+    }
   };
 
   App::new()
     .add_plugins((
       DefaultPlugins
         .set(ImagePlugin::default_nearest())
+        .set(WindowPlugin {
+          primary_window: Some(config.window()),
+          ..def()
+        })
         .set(AssetPlugin {
-          file_path: canonicalize("assets")
+          file_path: canonicalize(config.assets())
             .unwrap()
             .to_string_lossy()
             .to_owned()
@@ -85,99 +179,126 @@ fn main() {
           ..def()
         }),
       WorldInspectorPlugin::default(),
+      fk::FkPlugin,
     ))
-    .add_systems(Update, game_update)
+    .insert_resource(actions::default_actions())
+    .init_resource::<BuildDiagnostics>()
+    .add_systems(PreUpdate, update_actions)
+    .add_systems(FixedUpdate, game_fixed_update)
+    .add_systems(
+      Update,
+      (
+        game_update,
+        overlay::build_diagnostics_overlay,
+        overlay::game_panic_overlay,
+        overlay::stale_game_overlay,
+        overlay::game_load_error_overlay,
+        overlay::actions_load_error_overlay,
+      ),
+    )
     .run();
 }
 
-// // how many cells from the bottom to the top and from the left to the right
-// const CELLS: u32 = 20;
-// // TODO: should it also be rewritten?
-// pub fn grid_texture() -> Image {
-//   const WIDTH: usize = CELLS as usize
-//     // left and right borders
-//     + 2
-//     // // borders between cells
-//     // + (CELLS - 1)
-//     ;
-
-//   const TEXTURE_SIZE: usize =
-//     // width
-//     WIDTH
-//       // height
-//       * WIDTH
-//       // rgba
-//       * 4;
-
-//   let mut texture_data = [0; TEXTURE_SIZE];
-
-//   const WHITE_CELL: [u8; 4] = [255, 255, 255, 255];
-
-//   let wall: Vec<u8> = WHITE_CELL.repeat(WIDTH);
-
-//   let cells_start = WIDTH * 4;
-//   let cells_end = TEXTURE_SIZE - (WIDTH * 4);
-
-//   // top wall
-//   texture_data[0..cells_start].copy_from_slice(&wall);
-//   // bottom wall
-//   texture_data[cells_end..].copy_from_slice(&wall);
-
-//   // left and right walls
-//   for idx in (cells_start..(cells_end + 4)).step_by(WIDTH * 4) {
-//     texture_data[idx..(idx + 4)].copy_from_slice(&WHITE_CELL);
-//     texture_data[(idx - 4)..((idx - 4) + 4)].copy_from_slice(&WHITE_CELL);
-
-//     // --------------- inner walls ---------------
-//     //  (enable borders between cells in WIDTH if its needed)
-//     // let should_be_a_wall = (idx / (WIDTH * 4)) % 2 == 0;
-
-//     // for rel_idx in (0..WIDTH * 4).step_by(4) {
-//     //     if should_be_a_wall || (rel_idx / 4) % 2 == 0 {
-//     //         texture_data[(idx + rel_idx)..(idx + rel_idx) + 4].copy_from_slice(&WHITE_CELL);
-//     //     }
-//     // }
-//   }
-
-//   Image::new_fill(
-//     Extent3d {
-//       width: WIDTH as u32,
-//       height: WIDTH as u32,
-//       depth_or_array_layers: 1,
-//     },
-//     TextureDimension::D2,
-//     &texture_data,
-//     TextureFormat::Rgba8UnormSrgb,
-//     RenderAssetUsages::RENDER_WORLD,
-//   )
-// }
-
 type Game = Module<ModuleExports>;
 
 thread_local! {
   static GAME_INSTANCE: RefCell<(Option<Game>, bool)> = def();
+  // state saved by the previous module instance, see `Exports::save_state`
+  static SAVED_GAME_STATE: RefCell<Option<Vec<u8>>> = def();
+  // copy of the module made by live reload, removed after the module is unloaded
+  static GAME_MODULE_PATH: RefCell<Option<PathBuf>> = def();
+  // reported by the panic hook of the module, see `Imports::report_panic`
+  static REPORTED_GAME_PANIC: RefCell<Option<GamePanic>> = def();
+}
+
+/// Compares `shared::INTERFACE_HASH` of the loader with the one of the module,
+/// calling anything in a module with a different interface is undefined behavior
+fn check_interface_hash(path: &Path) -> Result<(), String> {
+  let module_hash = unsafe {
+    let library = libloading::Library::new(path).map_err(|e| e.to_string())?;
+    let hash = library
+      .get::<*const u64>(shared::INTERFACE_HASH_SYMBOL.as_bytes())
+      .map_err(|e| format!("failed to get interface hash of the game module: {e}"))?;
+    **hash
+  };
+
+  if module_hash != shared::INTERFACE_HASH {
+    return Err(
+      "interface of the game module (imports.rs or exports.rs of the shared crate) \
+      doesn't match the one the loader was built with, restart the loader"
+        .to_owned(),
+    );
+  }
+  Ok(())
 }
 
-fn load_game() {
+fn load_game(path: &Path) {
   GAME_INSTANCE.with_borrow_mut(|(instance, _)| {
-    let module = unsafe { load_module("target/debug/libgame.so", init_imports) };
+    let module = unsafe { load_module(path, init_imports) };
     let module: Game = module.unwrap();
     instance.replace(module);
   });
+  GAME_MODULE_PATH.set(Some(path.to_owned()));
 }
 
-fn unload_game() {
+fn unload_game(save_state: bool) {
   GAME_INSTANCE.with_borrow_mut(|(instance, setup_called)| {
     let Some(game) = instance.take() else {
       return;
     };
+    if save_state && *setup_called {
+      // if it panics, the world will be cleared and `setup` will be called on the next load
+      let state = unsafe { game.exports().save_state() };
+      let state = state.map(|state| unsafe { state.to_vec() });
+      SAVED_GAME_STATE.set(state);
+    }
     game.unload().unwrap();
     *setup_called = false;
   });
+
+  if let Some(path) = GAME_MODULE_PATH.take() {
+    fs::remove_file(path).unwrap();
+  }
+}
+
+fn game_fixed_update(world: &mut World) {
+  // setup is called (or state is restored) in the first update after the module is loaded
+  let setup_called =
+    GAME_INSTANCE.with_borrow(|(instance, setup_called)| instance.is_some() && *setup_called);
+  if !setup_called {
+    return;
+  }
+
+  let return_world = fk::take_world(world);
+  let returned = call_game_export(|game| unsafe { game.fixed_update() });
+  return_world(world);
+
+  if returned.is_none() {
+    on_game_panic(world);
+  }
+}
+
+fn report_game_panic(panic: GamePanic) {
+  REPORTED_GAME_PANIC.set(Some(panic));
 }
 
-fn call_game_export(call_: impl FnOnce(&ModuleExports)) {
-  GAME_INSTANCE.with_borrow(|(game, _)| {
-    call_(game.as_ref().unwrap().exports());
+/// Unloads the module which panicked in one of its exports and clears everything it spawned,
+/// the game will be loaded again after the next successful build
+fn on_game_panic(world: &mut World) {
+  let panic = REPORTED_GAME_PANIC.take().unwrap_or_else(|| GamePanic {
+    message: "game module panicked without reporting it (panic hook is not set?)".to_owned(),
+    backtrace: String::new(),
   });
+  eprintln!("{}\n{}", panic.message, panic.backtrace);
+  println!("game is unloaded until the next successful build");
+
+  unload_game(false);
+  SAVED_GAME_STATE.take();
+  fk::clear_world(world);
+  world.remove_resource::<StaleGame>();
+  world.insert_resource(panic);
+}
+
+fn call_game_export<R>(call_: impl FnOnce(&ModuleExports) -> R) -> R {
+  GAME_INSTANCE.with_borrow(|(game, _)| call_(game.as_ref().unwrap().exports()))
 }
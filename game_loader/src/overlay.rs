@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{
+  bevy_egui::EguiContexts,
+  egui::{self, Color32, RichText},
+};
+use cargo_metadata::diagnostic::DiagnosticLevel;
+use crate::live_reload::Diagnostic;
+
+/// Diagnostics of the last failed build of the game module, empty if the last build succeeded
+#[derive(Resource, Default)]
+pub struct BuildDiagnostics(pub Vec<Diagnostic>);
+
+/// Marks that the running game module is older than its sources because the last build failed
+#[derive(Resource)]
+pub struct StaleGame;
+
+pub fn stale_game_overlay(mut contexts: EguiContexts, stale: Option<Res<StaleGame>>) {
+  if stale.is_none() {
+    return;
+  }
+
+  egui::Area::new("stale_game".into())
+    .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+    .show(contexts.ctx_mut(), |ui| {
+      ui.label(
+        RichText::new("running the last successful build")
+          .color(Color32::YELLOW)
+          .background_color(Color32::from_black_alpha(200))
+          .strong(),
+      );
+    });
+}
+
+/// Error because of which the last successful build of the game module was not loaded
+#[derive(Resource)]
+pub struct GameLoadError(pub String);
+
+pub fn game_load_error_overlay(mut contexts: EguiContexts, error: Option<Res<GameLoadError>>) {
+  let Some(error) = error else {
+    return;
+  };
+
+  egui::Window::new("Failed to load the game")
+    .anchor(egui::Align2::CENTER_TOP, [0.0, 10.0])
+    .show(contexts.ctx_mut(), |ui| {
+      ui.label(RichText::new(&error.0).color(Color32::LIGHT_RED).strong());
+    });
+}
+
+/// Error of the last attempt to load the actions file, previous actions are used
+#[derive(Resource)]
+pub struct ActionsLoadError(pub String);
+
+pub fn actions_load_error_overlay(
+  mut contexts: EguiContexts,
+  error: Option<Res<ActionsLoadError>>,
+) {
+  let Some(error) = error else {
+    return;
+  };
+
+  egui::Window::new("Failed to load actions")
+    .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+    .show(contexts.ctx_mut(), |ui| {
+      ui.label(RichText::new(&error.0).color(Color32::LIGHT_RED).strong());
+      ui.label("previously loaded (or default) actions are used");
+    });
+}
+
+/// Panic of the game module, it stays unloaded until the next successful build
+#[derive(Resource)]
+pub struct GamePanic {
+  pub message: String,
+  pub backtrace: String,
+}
+
+pub fn game_panic_overlay(mut contexts: EguiContexts, panic: Option<Res<GamePanic>>) {
+  let Some(panic) = panic else {
+    return;
+  };
+
+  egui::Window::new("Game panicked")
+    .default_width(800.0)
+    .anchor(egui::Align2::CENTER_TOP, [0.0, 10.0])
+    .show(contexts.ctx_mut(), |ui| {
+      ui.label(
+        RichText::new(&panic.message)
+          .color(Color32::LIGHT_RED)
+          .strong(),
+      );
+      ui.label("game is unloaded until the next successful build");
+      egui::CollapsingHeader::new("Backtrace").show(ui, |ui| {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+          ui.label(RichText::new(&panic.backtrace).monospace());
+        });
+      });
+    });
+}
+
+pub fn build_diagnostics_overlay(mut contexts: EguiContexts, diagnostics: Res<BuildDiagnostics>) {
+  if diagnostics.0.is_empty() {
+    return;
+  }
+
+  egui::Window::new("Build failed")
+    .default_width(800.0)
+    .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -10.0])
+    .show(contexts.ctx_mut(), |ui| {
+      egui::ScrollArea::vertical().show(ui, |ui| {
+        for diagnostic in &diagnostics.0 {
+          let color = match diagnostic.level {
+            DiagnosticLevel::Ice | DiagnosticLevel::Error => Color32::LIGHT_RED,
+            DiagnosticLevel::Warning => Color32::YELLOW,
+            _ => Color32::LIGHT_GRAY,
+          };
+
+          let header = match &diagnostic.file {
+            Some(file) => format!(
+              "{file}:{}:{}: {}",
+              diagnostic.line, diagnostic.column, diagnostic.message
+            ),
+            None => diagnostic.message.clone(),
+          };
+          ui.label(RichText::new(header).color(color).strong());
+          ui.label(RichText::new(&diagnostic.rendered).monospace());
+          ui.separator();
+        }
+      });
+    });
+}
@@ -1,4 +1,14 @@
+use crate::abi_stable_types::RawSlice;
+
 pub trait Exports {
   fn setup();
   fn update();
+  /// Called from the `FixedUpdate` schedule of the host, see `Imports::set_fixed_timestep`
+  fn fixed_update();
+  /// Called right before the module is unloaded on live reload.
+  /// Returned bytes are copied by the host, they must stay valid until the module is unloaded.
+  fn save_state() -> RawSlice<u8>;
+  /// Called instead of `setup` with the bytes returned by `save_state` of the previous module instance.
+  /// If it returns false the world is cleared and `setup` is called.
+  fn restore_state(state: RawSlice<u8>) -> bool;
 }
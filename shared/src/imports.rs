@@ -1,16 +1,71 @@
-use fk_core::{BevyRawAssetIndex, KeyCode, PointLight, RawEntity, Rgba, Shape, StableTransform};
-use crate::abi_stable_types::Str;
+use fk_core::{
+  AmbientLight, BevyRawAssetIndex, Camera, DirectionalLight, Environment, FrameTime, GamepadAxis,
+  GamepadButton, KeyCode, Material, MouseButton, MouseScroll, PointLight, RawEntity, RawImage,
+  RawKeyEvent, RawMesh, Rgba, Shape, SpotLight, StableTransform, Vec2,
+};
+use crate::abi_stable_types::{RawSlice, StableOption, Str};
 
 pub trait Imports {
   fn despawn(entity: RawEntity);
+  fn set_parent(child: RawEntity, parent: RawEntity);
+  fn remove_parent(child: RawEntity);
   fn key_pressed(key_code: KeyCode) -> bool;
+  fn key_just_pressed(key_code: KeyCode) -> bool;
+  fn key_just_released(key_code: KeyCode) -> bool;
+  fn key_events() -> RawSlice<RawKeyEvent>;
+  fn mouse_pressed(button: MouseButton) -> bool;
+  fn mouse_just_pressed(button: MouseButton) -> bool;
+  fn mouse_just_released(button: MouseButton) -> bool;
+  fn cursor_position() -> StableOption<Vec2>;
+  fn mouse_motion() -> Vec2;
+  fn mouse_scroll() -> MouseScroll;
+  fn gamepads() -> RawSlice<RawEntity>;
+  fn gamepad_pressed(gamepad: RawEntity, button: GamepadButton) -> bool;
+  fn gamepad_just_pressed(gamepad: RawEntity, button: GamepadButton) -> bool;
+  fn gamepad_just_released(gamepad: RawEntity, button: GamepadButton) -> bool;
+  fn gamepad_axis(gamepad: RawEntity, axis: GamepadAxis) -> f32;
+  fn action_pressed(action: Str) -> bool;
+  fn action_just_pressed(action: Str) -> bool;
+  fn action_just_released(action: Str) -> bool;
+  fn frame_time() -> FrameTime;
+  fn set_fixed_timestep(seconds: f64);
   fn drop_asset(index: BevyRawAssetIndex);
   fn load_audio_asset(path: Str) -> BevyRawAssetIndex;
+  fn create_image(image: &RawImage) -> BevyRawAssetIndex;
+  fn load_image_asset(path: Str) -> BevyRawAssetIndex;
+  fn load_scene_asset(path: Str) -> BevyRawAssetIndex;
   fn begin_mut_entity_transform(entity: RawEntity) -> StableTransform;
   fn finish_mut_entity_transform(entity: RawEntity, mutated: &StableTransform);
   fn play_audio(asset: BevyRawAssetIndex) -> RawEntity;
-  fn spawn_camera(transform: &StableTransform) -> RawEntity;
+  fn spawn_camera(transform: &StableTransform, camera: &Camera) -> RawEntity;
+  fn set_camera(entity: RawEntity, camera: &Camera);
   fn spawn_color_mesh(transform: &StableTransform, shape: &Shape, color: Rgba) -> RawEntity;
   fn spawn_empty() -> RawEntity;
+  fn spawn_spatial(transform: &StableTransform) -> RawEntity;
+  fn create_mesh(mesh: &RawMesh) -> BevyRawAssetIndex;
+  fn update_mesh(mesh: BevyRawAssetIndex, data: &RawMesh);
+  fn create_material(material: &Material) -> BevyRawAssetIndex;
+  fn update_material(material: BevyRawAssetIndex, data: &Material);
+  fn spawn_material_mesh(
+    transform: &StableTransform,
+    shape: &Shape,
+    material: BevyRawAssetIndex,
+  ) -> RawEntity;
+  fn set_entity_material(entity: RawEntity, material: BevyRawAssetIndex);
+  fn spawn_scene(scene: BevyRawAssetIndex, transform: &StableTransform) -> RawEntity;
+  fn spawn_image_mesh(
+    transform: &StableTransform,
+    shape: &Shape,
+    image: BevyRawAssetIndex,
+  ) -> RawEntity;
+  fn spawn_mesh(transform: &StableTransform, mesh: BevyRawAssetIndex, color: Rgba) -> RawEntity;
   fn spawn_point_light(transform: &StableTransform, light: &PointLight) -> RawEntity;
+  fn set_point_light(entity: RawEntity, light: &PointLight);
+  fn spawn_directional_light(transform: &StableTransform, light: &DirectionalLight) -> RawEntity;
+  fn set_directional_light(entity: RawEntity, light: &DirectionalLight);
+  fn spawn_spot_light(transform: &StableTransform, light: &SpotLight) -> RawEntity;
+  fn set_spot_light(entity: RawEntity, light: &SpotLight);
+  fn set_ambient_light(light: &AmbientLight);
+  fn set_environment(environment: &Environment);
+  fn report_panic(message: Str, backtrace: Str);
 }
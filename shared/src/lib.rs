@@ -1,6 +1,34 @@
 pub mod exports;
 pub mod imports;
-pub mod abi_stable_types;
+pub use fk_core::abi_stable_types;
 
 pub const EXPORTS: &str = include_str!("exports.rs");
 pub const IMPORTS: &str = include_str!("imports.rs");
+
+/// Hash of the interface, the host refuses to load a module which was built with a different one
+/// (e.g. if `imports.rs` or a type of `fk_core` was changed but only the module was rebuilt)
+pub const INTERFACE_HASH: u64 = fnv1a(&[
+  IMPORTS.as_bytes(),
+  EXPORTS.as_bytes(),
+  fk_core::LIB_SOURCE.as_bytes(),
+  fk_core::ABI_STABLE_TYPES_SOURCE.as_bytes(),
+]);
+
+/// Name of the `static SHARED_INTERFACE_HASH: u64` exported by the module
+pub const INTERFACE_HASH_SYMBOL: &str = "SHARED_INTERFACE_HASH";
+
+const fn fnv1a(parts: &[&[u8]]) -> u64 {
+  let mut hash: u64 = 0xcbf29ce484222325;
+  let mut part_idx = 0;
+  while part_idx < parts.len() {
+    let part = parts[part_idx];
+    let mut idx = 0;
+    while idx < part.len() {
+      hash ^= part[idx] as u64;
+      hash = hash.wrapping_mul(0x100000001b3);
+      idx += 1;
+    }
+    part_idx += 1;
+  }
+  hash
+}